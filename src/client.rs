@@ -1,8 +1,10 @@
 mod auth;
 mod elasticsearch;
 mod host;
+mod kibana;
 pub mod setup;
 
 pub use auth::{Auth, AuthType};
 pub use elasticsearch::{index_template::*, ElasticsearchBuilder};
 pub use host::Host;
+pub use kibana::KibanaClient;
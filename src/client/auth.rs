@@ -18,7 +18,11 @@ impl Auth {
         match (r#type, username, password, apikey) {
             (AuthType::Apikey, _, _, Some(apikey)) => Self::Apikey(apikey),
             (AuthType::Basic, Some(username), Some(password), _) => Self::Basic(username, password),
-            (AuthType::None, _, _, _) | _ => Self::None,
+            (AuthType::None, _, _, _) => Self::None,
+            (r#type, _, _, _) => {
+                log::warn!("{type:?} authentication requested but its credentials are not set");
+                Self::None
+            }
         }
     }
 }
@@ -70,7 +70,7 @@ impl ElasticsearchBuilder {
             http::headers::AUTHORIZATION,
             http::headers::HeaderValue::from_str(&format!(
                 "Basic {}",
-                STANDARD.encode(&format!("{}:{}", username, password))
+                STANDARD.encode(format!("{}:{}", username, password))
             ))
             .expect("Invalid basic auth"),
         );
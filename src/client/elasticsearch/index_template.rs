@@ -1,16 +1,13 @@
 use serde_json::{json, Value};
-use std::cell::LazyCell;
+use std::sync::LazyLock;
 
-pub const INDEX_TEMPLATE: LazyCell<Value> = LazyCell::new(|| {
+/// Name of the ILM policy and settings component template shared by every data stream
+pub const LIFECYCLE_NAME: &str = "eshipster";
+
+/// Mappings shared by every eshipster document
+pub static COMMON_MAPPINGS: LazyLock<Value> = LazyLock::new(|| {
     json!({
-      "index_patterns": [
-        "eshipster-shards"
-      ],
       "template": {
-        "settings": {
-          "number_of_shards": 1,
-          "number_of_replicas": 1
-        },
         "mappings": {
           "dynamic_templates": [
             {
@@ -23,6 +20,9 @@ pub const INDEX_TEMPLATE: LazyCell<Value> = LazyCell::new(|| {
             }
           ],
           "properties": {
+            "@timestamp": {
+              "type": "date"
+            },
             "data_stream": {
               "properties": {
                 "dataset": {
@@ -36,6 +36,32 @@ pub const INDEX_TEMPLATE: LazyCell<Value> = LazyCell::new(|| {
                 }
               }
             },
+            "node": {
+              "properties": {
+                "desired": {
+                  "type": "keyword",
+                  "ignore_above": 256
+                },
+                "name": {
+                  "type": "keyword",
+                  "ignore_above": 256
+                },
+                "roles": {
+                  "type": "keyword"
+                }
+              }
+            }
+          }
+        }
+      }
+    })
+});
+
+pub static SHARDS_MAPPINGS: LazyLock<Value> = LazyLock::new(|| {
+    json!({
+      "template": {
+        "mappings": {
+          "properties": {
             "index": {
               "properties": {
                 "name": {
@@ -48,6 +74,22 @@ pub const INDEX_TEMPLATE: LazyCell<Value> = LazyCell::new(|| {
                 }
               }
             },
+            "rates": {
+              "properties": {
+                "fetch_per_sec": {
+                  "type": "double"
+                },
+                "index_per_sec": {
+                  "type": "double"
+                },
+                "query_per_sec": {
+                  "type": "double"
+                },
+                "write_load": {
+                  "type": "double"
+                }
+              }
+            },
             "shard": {
               "properties": {
                 "node": {
@@ -160,6 +202,16 @@ pub const INDEX_TEMPLATE: LazyCell<Value> = LazyCell::new(|| {
                       "type": "long"
                     }
                   }
+                },
+                "store": {
+                  "properties": {
+                    "reserved_in_bytes": {
+                      "type": "long"
+                    },
+                    "size_in_bytes": {
+                      "type": "long"
+                    }
+                  }
                 }
               }
             }
@@ -168,3 +220,171 @@ pub const INDEX_TEMPLATE: LazyCell<Value> = LazyCell::new(|| {
       }
     })
 });
+
+pub static NODES_MAPPINGS: LazyLock<Value> = LazyLock::new(|| {
+    let load = json!({
+      "properties": {
+        "bytes": {
+          "type": "long"
+        },
+        "fetch_per_sec": {
+          "type": "double"
+        },
+        "primaries": {
+          "type": "long"
+        },
+        "query_per_sec": {
+          "type": "double"
+        },
+        "shards": {
+          "type": "long"
+        },
+        "write_load": {
+          "type": "double"
+        }
+      }
+    });
+    json!({
+      "template": {
+        "mappings": {
+          "properties": {
+            "current": load,
+            "desired": load
+          }
+        }
+      }
+    })
+});
+
+pub static SUMMARY_MAPPINGS: LazyLock<Value> = LazyLock::new(|| {
+    let score = json!({
+      "properties": {
+        "bytes": {
+          "type": "double"
+        },
+        "shards": {
+          "type": "double"
+        },
+        "write_load": {
+          "type": "double"
+        }
+      }
+    });
+    json!({
+      "template": {
+        "mappings": {
+          "properties": {
+            "after": score,
+            "before": score,
+            "moves": {
+              "type": "long"
+            },
+            "nodes": {
+              "type": "long"
+            },
+            "relocating_bytes": {
+              "type": "long"
+            },
+            "shards": {
+              "type": "long"
+            },
+            "strategy": {
+              "type": "keyword"
+            }
+          }
+        }
+      }
+    })
+});
+
+/// Data streams written by the exporters, with the name of their templates and their own mappings
+pub fn data_streams() -> [(&'static str, &'static str, &'static Value); 3] {
+    [
+        (
+            "metrics-shards-eshipster",
+            "eshipster-shards",
+            &SHARDS_MAPPINGS,
+        ),
+        (
+            "metrics-nodes-eshipster",
+            "eshipster-nodes",
+            &NODES_MAPPINGS,
+        ),
+        (
+            "metrics-summary-eshipster",
+            "eshipster-summary",
+            &SUMMARY_MAPPINGS,
+        ),
+    ]
+}
+
+/// Index settings shared by every data stream, attaching the ILM policy when one is used
+pub fn settings_template(ilm: bool) -> Value {
+    let mut settings = json!({
+      "number_of_shards": 1,
+      "number_of_replicas": 1
+    });
+    if ilm {
+        settings["lifecycle"] = json!({ "name": LIFECYCLE_NAME });
+    }
+    json!({
+      "template": {
+        "settings": settings
+      }
+    })
+}
+
+/// A data stream template composed of the shared and type-specific component templates. With
+/// a data stream lifecycle retention, the template manages retention instead of ILM.
+pub fn index_template(data_stream: &str, name: &str, dsl_retention: Option<&str>) -> Value {
+    let mut index_template = json!({
+      "index_patterns": [
+        data_stream
+      ],
+      "data_stream": {},
+      "priority": 200,
+      "composed_of": [
+        "eshipster@settings",
+        "eshipster@mappings",
+        format!("{name}@mappings")
+      ],
+      "_meta": {
+        "managed_by": "eshipster"
+      }
+    });
+    if let Some(retention) = dsl_retention {
+        index_template["template"] = json!({
+          "lifecycle": {
+            "data_retention": retention
+          }
+        });
+    }
+    index_template
+}
+
+/// Roll over daily or at 50GB per primary shard, deleting indices once they reach the retention
+pub fn ilm_policy(retention: &str) -> Value {
+    json!({
+      "policy": {
+        "phases": {
+          "hot": {
+            "actions": {
+              "rollover": {
+                "max_age": "1d",
+                "max_primary_shard_size": "50gb"
+              }
+            }
+          },
+          "delete": {
+            "min_age": retention,
+            "actions": {
+              "delete": {}
+            }
+          }
+        },
+        "_meta": {
+          "managed_by": "eshipster"
+        }
+      }
+    })
+}
@@ -1,6 +1,5 @@
 use color_eyre::eyre::Result;
 use serde::{Deserialize, Serialize};
-use serde_yaml;
 use std::collections::BTreeMap;
 use std::env;
 use std::fmt::{self, Display, Formatter};
@@ -42,12 +41,7 @@ impl Host {
         };
         log::debug!(
             "Known hosts: {}",
-            hosts
-                .clone()
-                .into_iter()
-                .map(|(k, _)| k)
-                .collect::<Vec<String>>()
-                .join(", ")
+            hosts.keys().cloned().collect::<Vec<String>>().join(", ")
         );
         hosts.get(host).cloned()
     }
@@ -74,7 +68,7 @@ impl Display for Host {
 impl FromStr for Host {
     type Err = ();
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match Host::parse(&s.to_string()) {
+        match Host::parse(s) {
             Some(host) => Ok(host),
             None => Err(()),
         }
@@ -86,7 +80,7 @@ fn get_hosts_path() -> Result<PathBuf> {
     match env::var("ESHIPSTER_HOSTS") {
         Ok(path) => Ok(PathBuf::from(path)),
         Err(_) => {
-            let home = env::var("HOME").map(|home| PathBuf::from(home))?;
+            let home = env::var("HOME").map(PathBuf::from)?;
             // Check if the `.eshipster` directory exists, if not, create it
             let eshipster_dir = home.join(".eshipster");
             if !eshipster_dir.exists() {
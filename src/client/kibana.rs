@@ -0,0 +1,165 @@
+use super::auth::{Auth, AuthType};
+use super::host::Host;
+use crate::config;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use color_eyre::eyre::{eyre, Result};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+use url::Url;
+
+/// Saved objects for the eshipster data view and visualizations
+const SAVED_OBJECTS: &str = include_str!("../../assets/kibana/eshipster.ndjson");
+
+/// A minimal Kibana client for provisioning saved objects
+pub struct KibanaClient {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl KibanaClient {
+    /// Create a new KibanaClient from a URL and Auth
+    pub fn new(mut url: Url, auth: Auth, insecure: bool) -> Result<Self> {
+        // Keep any base path when joining API paths onto the URL
+        if !url.path().ends_with('/') {
+            url.set_path(&format!("{}/", url.path()));
+        }
+        let mut headers = HeaderMap::new();
+        // Kibana rejects API requests without this header as a CSRF protection
+        headers.insert("kbn-xsrf", HeaderValue::from_static("true"));
+        let authorization = match auth {
+            Auth::Apikey(apikey) => Some(format!("ApiKey {apikey}")),
+            Auth::Basic(username, password) => Some(format!(
+                "Basic {}",
+                STANDARD.encode(format!("{username}:{password}"))
+            )),
+            Auth::None => None,
+        };
+        if let Some(authorization) = authorization {
+            headers.insert(AUTHORIZATION, HeaderValue::from_str(&authorization)?);
+        }
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .danger_accept_invalid_certs(insecure)
+            .build()?;
+        Ok(Self { client, url })
+    }
+
+    /// Create a new KibanaClient from a Host
+    pub fn from_host(host: Host) -> Result<Self> {
+        match host {
+            Host::ApiKey {
+                apikey,
+                url,
+                insecure,
+            } => Self::new(url, Auth::Apikey(apikey), insecure.unwrap_or(false)),
+            Host::Basic {
+                insecure,
+                username,
+                password,
+                url,
+            } => Self::new(
+                url,
+                Auth::Basic(username, password),
+                insecure.unwrap_or(false),
+            ),
+            Host::None { url, insecure } => Self::new(url, Auth::None, insecure.unwrap_or(false)),
+        }
+    }
+
+    pub fn parse(kibana: &str, auth_type: &AuthType) -> Result<Self> {
+        log::debug!("Parsing Kibana: {}", kibana);
+        if let Some(host) = Host::parse(kibana) {
+            return Self::from_host(host);
+        }
+        match Url::parse(kibana) {
+            Ok(url) => {
+                let auth = Auth::new(
+                    auth_type,
+                    config::ESHIPSTER_XP_USERNAME.clone(),
+                    config::ESHIPSTER_XP_PASSWORD.clone(),
+                    config::ESHIPSTER_XP_APIKEY.clone(),
+                );
+                Self::new(url, auth, true)
+            }
+            Err(_) => Err(eyre!("Kibana must be a known host or a URL")),
+        }
+    }
+
+    /// Import the bundled saved objects, replacing existing objects with the same IDs only when
+    /// `overwrite` is set. Returns the number of objects imported.
+    pub async fn import_saved_objects(&self, overwrite: bool) -> Result<usize> {
+        let mut url = self.url.join("api/saved_objects/_import")?;
+        url.query_pairs_mut()
+            .append_pair("overwrite", &overwrite.to_string());
+        let file = Part::text(SAVED_OBJECTS)
+            .file_name("eshipster.ndjson")
+            .mime_str("application/ndjson")?;
+        let response = self
+            .client
+            .post(url)
+            .multipart(Form::new().part("file", file))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await?;
+            return Err(eyre!("Kibana import failed with {status}: {body}"));
+        }
+        let result = response.json::<ImportResponse>().await?;
+        for error in &result.errors {
+            log::warn!(
+                "Failed to import {} {} ({}): {}",
+                error.r#type,
+                error.title.as_deref().unwrap_or_default(),
+                error.id,
+                error.error.r#type
+            );
+        }
+        let conflicts = result
+            .errors
+            .iter()
+            .filter(|e| e.error.r#type == "conflict")
+            .count();
+        if conflicts > 0 {
+            log::warn!("{conflicts} saved objects already exist, use --overwrite to replace them");
+        }
+        match result.success || result.errors.len() == conflicts {
+            true => Ok(result.success_count),
+            false => Err(eyre!(
+                "{} saved objects failed to import",
+                result.errors.len()
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportResponse {
+    success: bool,
+    success_count: usize,
+    #[serde(default)]
+    errors: Vec<ImportError>,
+}
+
+#[derive(Deserialize)]
+struct ImportError {
+    id: String,
+    r#type: String,
+    title: Option<String>,
+    error: ImportErrorReason,
+}
+
+#[derive(Deserialize)]
+struct ImportErrorReason {
+    r#type: String,
+}
+
+impl std::fmt::Display for KibanaClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.url)
+    }
+}
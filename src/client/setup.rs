@@ -1,28 +1,122 @@
-use super::INDEX_TEMPLATE;
+use super::KibanaClient;
+use super::{
+    data_streams, ilm_policy, index_template, settings_template, COMMON_MAPPINGS, LIFECYCLE_NAME,
+};
 use crate::exporter::Exporter;
+use clap::ValueEnum;
 use color_eyre::eyre::{eyre, Result};
+use serde_json::{json, Value};
 
-pub async fn elasticsearch(exporter: &Exporter) -> Result<()> {
-    match exporter {
-        Exporter::Elasticsearch(client) => {
-            let index_template = &*INDEX_TEMPLATE;
-            let response = client
-                .send(
-                    "PUT",
-                    "_index_template/eshipster-shards",
-                    Some(index_template),
-                )
-                .await?;
-            if response.status_code().is_success() {
-                log::info!("Succesfully setup index template");
-                Ok(())
-            } else {
-                log::warn!("Failed to setup index template");
-                log::debug!("{:?}", &response);
-                let body = response.text().await?;
-                Err(eyre!("Failed to setup index template: {}", &body))
-            }
-        }
-        _ => Err(eyre!("Can only setup an Elasticsearch host")),
+/// How old eshipster documents are deleted
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Lifecycle {
+    /// An index lifecycle management policy
+    Ilm,
+    /// The data stream lifecycle
+    Dsl,
+}
+
+/// Install the lifecycle policy, component templates and data stream templates used by the
+/// exporters, deleting documents once they are older than the retention period
+pub async fn elasticsearch(
+    exporter: &Exporter,
+    lifecycle: Lifecycle,
+    retention: &str,
+) -> Result<()> {
+    if !matches!(exporter, Exporter::Elasticsearch(_)) {
+        return Err(eyre!("Can only setup an Elasticsearch host"));
+    }
+
+    let ilm = matches!(lifecycle, Lifecycle::Ilm);
+    if ilm {
+        let path = format!("_ilm/policy/{LIFECYCLE_NAME}");
+        put(exporter, &path, &ilm_policy(retention)).await?;
+    }
+    put(
+        exporter,
+        "_component_template/eshipster@settings",
+        &settings_template(ilm),
+    )
+    .await?;
+    put(
+        exporter,
+        "_component_template/eshipster@mappings",
+        &COMMON_MAPPINGS,
+    )
+    .await?;
+
+    let dsl_retention = match lifecycle {
+        Lifecycle::Ilm => None,
+        Lifecycle::Dsl => Some(retention),
+    };
+    for (data_stream, name, mappings) in data_streams() {
+        put(
+            exporter,
+            &format!("_component_template/{name}@mappings"),
+            mappings,
+        )
+        .await?;
+        let template = index_template(data_stream, name, dsl_retention);
+        put(exporter, &format!("_index_template/{name}"), &template).await?;
+    }
+    Ok(())
+}
+
+/// Set `index.number_of_shards` in an index template, keeping the rest of the template as is.
+/// Templates managed by Elasticsearch are reinstalled on upgrades, which would revert the
+/// change, so they are skipped. Returns whether the template was updated.
+pub async fn template_shards(exporter: &Exporter, template: &str, shards: u64) -> Result<bool> {
+    let Exporter::Elasticsearch(client) = exporter else {
+        return Err(eyre!("Can only update templates on an Elasticsearch host"));
+    };
+    let path = format!("_index_template/{template}");
+    let response = client.send("GET", &path, None).await?;
+    if !response.status_code().is_success() {
+        return Err(eyre!("Index template {template} not found"));
+    }
+    let mut body = response.json::<Value>().await?;
+    let mut index_template = body["index_templates"][0]["index_template"].take();
+    if index_template.is_null() {
+        return Err(eyre!("Index template {template} not found"));
+    }
+    if index_template["_meta"]["managed"].as_bool() == Some(true) {
+        log::warn!(
+            "Skipping {template}, which is managed by Elasticsearch, set index.number_of_shards \
+             in {template}@custom instead"
+        );
+        return Ok(false);
+    }
+
+    // Settings may be flat or nested, so drop any flat keys before setting the nested one
+    let settings = &mut index_template["template"]["settings"];
+    if let Some(settings) = settings.as_object_mut() {
+        settings.remove("index.number_of_shards");
+        settings.remove("number_of_shards");
+    }
+    settings["index"]["number_of_shards"] = json!(shards.to_string());
+    put(exporter, &path, &index_template).await?;
+    Ok(true)
+}
+
+/// Import the eshipster data view and visualizations into Kibana
+pub async fn kibana(client: &KibanaClient, overwrite: bool) -> Result<()> {
+    let imported = client.import_saved_objects(overwrite).await?;
+    log::info!("Imported {imported} saved objects into Kibana {client}");
+    Ok(())
+}
+
+async fn put(exporter: &Exporter, path: &str, body: &Value) -> Result<()> {
+    let Exporter::Elasticsearch(client) = exporter else {
+        return Err(eyre!("Can only setup an Elasticsearch host"));
+    };
+    let response = client.send("PUT", path, Some(body)).await?;
+    if response.status_code().is_success() {
+        log::info!("Succesfully setup {path}");
+        Ok(())
+    } else {
+        log::warn!("Failed to setup {path}");
+        log::debug!("{:?}", &response);
+        let body = response.text().await?;
+        Err(eyre!("Failed to setup {path}: {}", &body))
     }
 }
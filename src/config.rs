@@ -10,8 +10,6 @@ pub static LOG_LEVEL: &str = "info";
 
 type Setting = LazyLock<Option<String>>;
 
-pub static START_TIME: LazyLock<i64> = LazyLock::new(|| chrono::Utc::now().timestamp_millis());
-
 pub static ESHIPSTER_RC_USERNAME: Setting =
     LazyLock::new(|| std::env::var("ESHIPSTER_RC_USERNAME").ok());
 pub static ESHIPSTER_RC_PASSWORD: Setting =
@@ -24,3 +22,26 @@ pub static ESHIPSTER_XP_PASSWORD: Setting =
     LazyLock::new(|| std::env::var("ESHIPSTER_XP_PASSWORD").ok());
 pub static ESHIPSTER_XP_APIKEY: Setting =
     LazyLock::new(|| std::env::var("ESHIPSTER_XP_APIKEY").ok());
+
+type Limit = LazyLock<usize>;
+
+/// Maximum documents in one `_bulk` request
+pub static ESHIPSTER_BULK_DOCS: Limit = LazyLock::new(|| limit("ESHIPSTER_BULK_DOCS", 5_000));
+/// Maximum bytes of documents in one `_bulk` request, well under the default 100mb
+/// `http.max_content_length`
+pub static ESHIPSTER_BULK_BYTES: Limit =
+    LazyLock::new(|| limit("ESHIPSTER_BULK_BYTES", 10 * 1024 * 1024));
+/// Maximum `_bulk` requests in flight at once
+pub static ESHIPSTER_BULK_CONCURRENCY: Limit =
+    LazyLock::new(|| limit("ESHIPSTER_BULK_CONCURRENCY", 2));
+
+fn limit(name: &str, default: usize) -> usize {
+    match std::env::var(name).ok().map(|value| value.parse::<usize>()) {
+        Some(Ok(value)) if value > 0 => value,
+        Some(_) => {
+            log::warn!("Invalid {name}, using the default of {default}");
+            default
+        }
+        None => default,
+    }
+}
@@ -1,11 +1,25 @@
+mod cluster_settings;
 mod data_streams;
 mod elasticsearch_api;
+mod export_doc;
 mod indices_settings;
 mod nodes;
+mod nodes_stats;
+mod placements;
+mod recoveries;
+mod reroute;
 mod shards;
+mod summary;
 
+pub use cluster_settings::*;
 pub use data_streams::*;
 pub use elasticsearch_api::*;
+pub use export_doc::*;
 pub use indices_settings::*;
 pub use nodes::*;
+pub use nodes_stats::*;
+pub use placements::*;
+pub use recoveries::*;
+pub use reroute::*;
 pub use shards::*;
+pub use summary::*;
@@ -0,0 +1,83 @@
+use super::ElasticsearchApi;
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Default, Deserialize)]
+pub struct ClusterSettings {
+    #[serde(default)]
+    defaults: Value,
+    #[serde(default)]
+    persistent: Value,
+    #[serde(default)]
+    transient: Value,
+}
+
+impl ClusterSettings {
+    /// Get the effective value of a dotted setting key, in order of transient, persistent, and
+    /// default precedence. Works with both flat and nested settings responses.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        [&self.transient, &self.persistent, &self.defaults]
+            .into_iter()
+            .find_map(|settings| find_setting(settings, key))
+    }
+
+    /// Get a setting holding a comma-separated list or an array of strings
+    pub fn get_list(&self, key: &str) -> Vec<String> {
+        match self.get(key) {
+            Some(Value::String(list)) => list
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect(),
+            Some(Value::Array(list)) => list
+                .iter()
+                .filter_map(|item| item.as_str().map(String::from))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Get a setting holding a number, which the settings API usually wraps in quotes
+    pub fn get_i64(&self, key: &str) -> Option<i64> {
+        match self.get(key)? {
+            Value::Number(num) => num.as_i64(),
+            Value::String(s) => s.parse::<i64>().ok(),
+            _ => None,
+        }
+    }
+
+    /// Get a setting holding a boolean, which the settings API usually wraps in quotes
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            Value::Bool(b) => Some(*b),
+            Value::String(s) => s.parse::<bool>().ok(),
+            _ => None,
+        }
+    }
+}
+
+fn find_setting<'a>(settings: &'a Value, key: &str) -> Option<&'a Value> {
+    if let Some(value) = settings.get(key) {
+        return Some(value);
+    }
+    // Walk each possible split of the dotted key into a nested object
+    key.match_indices('.').find_map(|(i, _)| {
+        settings
+            .get(&key[..i])
+            .and_then(|child| find_setting(child, &key[i + 1..]))
+    })
+}
+
+impl ElasticsearchApi for ClusterSettings {
+    fn url_path() -> String {
+        "_cluster/settings?include_defaults=true&flat_settings=true".to_string()
+    }
+    fn file_name() -> String {
+        "cluster_settings_defaults.json".to_string()
+    }
+    fn alternative_file_names() -> Vec<String> {
+        // Older diagnostics only collect the explicitly set settings
+        vec!["cluster_settings.json".to_string()]
+    }
+}
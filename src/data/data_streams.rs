@@ -25,6 +25,9 @@ pub struct DataStream {
     pub system: Option<bool>,
     pub template: String,
     pub timestamp_field: TimestampField,
+    /// The backing index currently receiving writes, identified by the data stream lookup
+    #[serde(skip_deserializing)]
+    pub write_index: Option<String>,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -48,4 +51,7 @@ impl ElasticsearchApi for DataStreams {
     fn file_name() -> String {
         "commercial/data_stream.json".to_string()
     }
+    fn alternative_file_names() -> Vec<String> {
+        vec!["data_stream.json".to_string()]
+    }
 }
@@ -1,4 +1,9 @@
 pub trait ElasticsearchApi {
     fn file_name() -> String;
     fn url_path() -> String;
+    /// File names used for the same API by other versions of the support diagnostic, tried in
+    /// order when `file_name` is missing
+    fn alternative_file_names() -> Vec<String> {
+        Vec::new()
+    }
 }
@@ -0,0 +1,36 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// The `data_stream` fields of an exported document
+#[derive(Clone, Serialize)]
+pub struct DataStreamName {
+    r#type: &'static str,
+    dataset: &'static str,
+    namespace: &'static str,
+}
+
+impl DataStreamName {
+    pub fn metrics(dataset: &'static str) -> Self {
+        Self {
+            r#type: "metrics",
+            dataset,
+            namespace: "eshipster",
+        }
+    }
+}
+
+/// A document written by the exporters, each type to its own data stream
+pub trait ExportDoc: Serialize {
+    /// The data stream to index documents of this type into
+    fn data_stream() -> &'static str;
+
+    fn as_value(&self) -> Value {
+        match serde_json::to_value(self) {
+            Ok(value) => value,
+            Err(e) => {
+                log::error!("Failed to serialize {} doc: {}", Self::data_stream(), e);
+                Value::Null
+            }
+        }
+    }
+}
@@ -51,6 +51,96 @@ impl IndexSettings {
             ..self
         }
     }
+
+    pub fn number_of_shards(&self) -> Option<i64> {
+        self.number_of_shards
+    }
+
+    pub fn number_of_replicas(&self) -> Option<i64> {
+        self.number_of_replicas
+    }
+
+    /// The ordered `index.routing.allocation.include._tier_preference` list
+    pub fn tier_preference(&self) -> Vec<String> {
+        self.routing
+            .as_ref()
+            .and_then(|routing| {
+                routing
+                    .pointer("/allocation/include/_tier_preference")
+                    .or(routing.get("allocation.include._tier_preference"))
+            })
+            .and_then(|tiers| tiers.as_str())
+            .map(|tiers| {
+                tiers
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|tier| !tier.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The `index.routing.allocation.total_shards_per_node` limit, if one is set
+    pub fn total_shards_per_node(&self) -> Option<i64> {
+        [&self.routing, &self.allocation]
+            .into_iter()
+            .flatten()
+            .find_map(|settings| {
+                let limit = settings
+                    .pointer("/allocation/total_shards_per_node")
+                    .or(settings.get("total_shards_per_node"))?;
+                match limit {
+                    Value::Number(num) => num.as_i64(),
+                    Value::String(s) => s.parse::<i64>().ok(),
+                    _ => None,
+                }
+            })
+            // Elasticsearch uses -1 to mean unbounded
+            .filter(|limit| *limit > 0)
+    }
+
+    /// Whether this index is the write index of its data stream
+    pub fn is_write_index(&self) -> bool {
+        self.data_stream
+            .as_ref()
+            .and_then(|data_stream| data_stream.write_index.as_ref())
+            .is_some_and(|write_index| Some(write_index) == self.name.as_ref())
+    }
+
+    /// Whether the `index.routing.allocation.include._name` filter was set by eshipster, which
+    /// marks its pins with `index.routing.allocation.exclude.eshipster_pin`
+    pub fn has_pin_marker(&self) -> bool {
+        self.routing
+            .as_ref()
+            .and_then(|routing| {
+                routing
+                    .pointer("/allocation/exclude/eshipster_pin")
+                    .or(routing.get("allocation.exclude.eshipster_pin"))
+            })
+            .is_some_and(|marker| marker.as_str() == Some("true"))
+    }
+
+    /// Node names from the `index.routing.allocation.include._name` filter
+    pub fn pinned_nodes(&self) -> Vec<String> {
+        self.routing
+            .as_ref()
+            .and_then(|routing| {
+                routing
+                    .pointer("/allocation/include/_name")
+                    .or(routing.get("allocation.include._name"))
+            })
+            .and_then(|nodes| nodes.as_str())
+            .map(|nodes| {
+                nodes
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|node| !node.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
 }
 
 fn default_codec() -> String {
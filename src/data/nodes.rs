@@ -1,4 +1,4 @@
-use super::ElasticsearchApi;
+use super::{DataStreamName, ElasticsearchApi, ExportDoc, ShardDoc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -16,7 +16,8 @@ pub struct Node {
     index_version: Option<i64>,
     //ingest: Value,
     ip: String,
-    //jvm: Value,
+    #[serde(default, skip_serializing)]
+    jvm: Value,
     //modules: Value,
     pub name: String,
     pub desired: Option<String>,
@@ -33,12 +34,100 @@ pub struct Node {
     version: String,
 }
 
+impl Node {
+    /// Look up a custom node attribute such as `zone` or `rack`
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).and_then(|value| value.as_str())
+    }
+
+    /// Processors Elasticsearch sizes its thread pools by
+    pub fn allocated_processors(&self) -> Option<u64> {
+        self.os.get("allocated_processors")?.as_u64()
+    }
+
+    /// Maximum JVM heap size
+    pub fn heap_max_in_bytes(&self) -> Option<u64> {
+        self.jvm.pointer("/mem/heap_max_in_bytes")?.as_u64()
+    }
+
+    /// Whether the node can hold shards of a data tier, the generic `data` role holds every tier
+    pub fn in_tier(&self, tier: &str) -> bool {
+        self.roles.iter().any(|role| role == tier || role == "data")
+    }
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 struct ComponentVersion {
     ml_config_version: i64,
     transform_config_version: i64,
 }
 
+/// Shard totals of one node under either the current or the desired placement
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct NodeLoad {
+    shards: usize,
+    primaries: usize,
+    bytes: u64,
+    write_load: f64,
+    query_per_sec: f64,
+    fetch_per_sec: f64,
+}
+
+impl NodeLoad {
+    fn add(&mut self, shard: &ShardDoc) {
+        let stats = shard.stats();
+        self.shards += 1;
+        self.primaries += usize::from(shard.primary());
+        self.bytes += stats.size_in_bytes();
+        self.write_load += stats.write_load();
+        if let Some(rates) = shard.rates() {
+            self.query_per_sec += rates.query_per_sec;
+            self.fetch_per_sec += rates.fetch_per_sec;
+        }
+    }
+}
+
+/// One document per node and evaluation, with its load before and after the plan
+#[derive(Serialize)]
+pub struct NodeDoc {
+    data_stream: DataStreamName,
+    node: Node,
+    current: NodeLoad,
+    desired: NodeLoad,
+    #[serde(rename = "@timestamp")]
+    timestamp: i64,
+}
+
+impl NodeDoc {
+    pub fn new(node: Node, timestamp: i64) -> Self {
+        Self {
+            data_stream: DataStreamName::metrics("nodes"),
+            node,
+            current: NodeLoad::default(),
+            desired: NodeLoad::default(),
+            timestamp,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.node.name
+    }
+
+    pub fn add_current(&mut self, shard: &ShardDoc) {
+        self.current.add(shard);
+    }
+
+    pub fn add_desired(&mut self, shard: &ShardDoc) {
+        self.desired.add(shard);
+    }
+}
+
+impl ExportDoc for NodeDoc {
+    fn data_stream() -> &'static str {
+        "metrics-nodes-eshipster"
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct Nodes {
     //_nodes: Value,
@@ -0,0 +1,42 @@
+use super::ElasticsearchApi;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize)]
+pub struct NodesStats {
+    pub nodes: HashMap<String, NodeStats>,
+}
+
+#[derive(Deserialize)]
+pub struct NodeStats {
+    pub name: String,
+    fs: Option<FsStats>,
+}
+
+impl NodeStats {
+    /// Total and available bytes across the data paths of the node
+    pub fn disk(&self) -> Option<(u64, u64)> {
+        let total = &self.fs.as_ref()?.total;
+        Some((total.total_in_bytes?, total.available_in_bytes?))
+    }
+}
+
+#[derive(Deserialize)]
+struct FsStats {
+    total: FsTotal,
+}
+
+#[derive(Deserialize)]
+struct FsTotal {
+    total_in_bytes: Option<u64>,
+    available_in_bytes: Option<u64>,
+}
+
+impl ElasticsearchApi for NodesStats {
+    fn url_path() -> String {
+        "_nodes/stats/fs".to_string()
+    }
+    fn file_name() -> String {
+        "nodes_stats.json".to_string()
+    }
+}
@@ -0,0 +1,42 @@
+use super::{ElasticsearchApi, ShardMove};
+use serde::Deserialize;
+
+/// Where a shard copy is allocated, from the `_cat/shards` API
+#[derive(Deserialize)]
+pub struct ShardPlacement {
+    pub index: String,
+    pub shard: String,
+    pub prirep: String,
+    /// The node holding the copy, or `source -> ip id target` while it relocates
+    pub node: Option<String>,
+    pub state: String,
+}
+
+impl ShardPlacement {
+    /// Whether this is the copy a move relocates, on either end of it
+    pub fn is_copy_of(&self, shard_move: &ShardMove) -> bool {
+        let prirep = match shard_move.primary {
+            true => "p",
+            false => "r",
+        };
+        self.index == shard_move.index
+            && self.shard == shard_move.shard.to_string()
+            && self.prirep == prirep
+    }
+
+    /// The node the copy is allocated on, ignoring any relocation target
+    pub fn node_name(&self) -> Option<&str> {
+        self.node.as_deref()?.split_whitespace().next()
+    }
+}
+
+pub type ShardPlacements = Vec<ShardPlacement>;
+
+impl ElasticsearchApi for ShardPlacements {
+    fn url_path() -> String {
+        "_cat/shards?format=json&h=index,shard,prirep,node,state".to_string()
+    }
+    fn file_name() -> String {
+        "cat/cat_shards.json".to_string()
+    }
+}
@@ -0,0 +1,36 @@
+use super::{ElasticsearchApi, ShardMove};
+use serde::Deserialize;
+
+/// An active shard recovery from the `_cat/recovery` API
+#[derive(Deserialize)]
+pub struct Recovery {
+    pub index: String,
+    pub shard: String,
+    pub stage: String,
+    pub source_node: Option<String>,
+    pub target_node: Option<String>,
+    pub r#type: String,
+}
+
+impl Recovery {
+    /// Whether this is the peer recovery carrying out a move
+    pub fn relocates(&self, shard_move: &ShardMove) -> bool {
+        self.r#type == "peer"
+            && self.index == shard_move.index
+            && self.shard == shard_move.shard.to_string()
+            && self.source_node.as_deref() == Some(shard_move.from_node.as_str())
+            && self.target_node.as_deref() == Some(shard_move.to_node.as_str())
+    }
+}
+
+pub type Recoveries = Vec<Recovery>;
+
+impl ElasticsearchApi for Recoveries {
+    fn url_path() -> String {
+        "_cat/recovery?active_only=true&format=json&h=index,shard,stage,source_node,target_node,type"
+            .to_string()
+    }
+    fn file_name() -> String {
+        "cat/cat_recovery.json".to_string()
+    }
+}
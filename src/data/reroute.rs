@@ -0,0 +1,58 @@
+use super::ShardDoc;
+use serde::Serialize;
+
+/// A single shard relocation from one node to another
+#[derive(Clone, PartialEq, Serialize)]
+pub struct ShardMove {
+    pub index: String,
+    pub shard: u16,
+    #[serde(skip_serializing)]
+    pub primary: bool,
+    #[serde(skip_serializing)]
+    pub bytes: u64,
+    pub from_node: String,
+    pub to_node: String,
+}
+
+impl ShardMove {
+    /// Whether this move relocates the given shard copy from where it currently is
+    pub fn is_move_of(&self, shard: &ShardDoc) -> bool {
+        self.index == shard.index_name()
+            && self.shard == shard.shard_number()
+            && shard.node_name() == Some(self.from_node.as_str())
+    }
+}
+
+impl std::fmt::Display for ShardMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let copy = match self.primary {
+            true => "p",
+            false => "r",
+        };
+        write!(
+            f,
+            "{}[{}] {}: {} -> {}",
+            self.index, self.shard, copy, self.from_node, self.to_node
+        )
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RerouteCommand {
+    Move(ShardMove),
+}
+
+/// Request body for the `_cluster/reroute` API
+#[derive(Serialize)]
+pub struct RerouteCommands {
+    pub commands: Vec<RerouteCommand>,
+}
+
+impl From<&ShardMove> for RerouteCommands {
+    fn from(shard_move: &ShardMove) -> Self {
+        Self {
+            commands: vec![RerouteCommand::Move(shard_move.clone())],
+        }
+    }
+}
@@ -1,5 +1,4 @@
-use super::{ElasticsearchApi, IndexSettings, Node};
-use crate::config;
+use super::{DataStreamName, ElasticsearchApi, ExportDoc, IndexSettings, Node};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -19,23 +18,74 @@ pub struct IndexStats {
     // uuid: String,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct ShardStats {
     docs: DocStats,
     indexing: IndexingStats,
     search: SearchStats,
+    store: StoreStats,
     #[serde(skip_serializing)]
     pub routing: ShardRouting,
 }
 
-#[derive(Deserialize, Serialize)]
+impl ShardStats {
+    /// Bytes the shard takes on disk, including what is reserved for an ongoing recovery or
+    /// merge, as the disk allocation decider counts it
+    pub fn size_in_bytes(&self) -> u64 {
+        self.store.size_in_bytes + self.store.reserved_in_bytes.unwrap_or_default().max(0) as u64
+    }
+
+    pub fn write_load(&self) -> f64 {
+        self.indexing.write_load
+    }
+
+    /// Total query and fetch operations, the search phases that consume shard resources
+    pub fn search_total(&self) -> u64 {
+        self.search.query_total + self.search.fetch_total
+    }
+
+    pub fn index_total(&self) -> u64 {
+        self.indexing.index_total
+    }
+
+    pub fn index_time_in_millis(&self) -> u64 {
+        self.indexing.index_time_in_millis
+    }
+
+    pub fn query_total(&self) -> u64 {
+        self.search.query_total
+    }
+
+    pub fn fetch_total(&self) -> u64 {
+        self.search.fetch_total
+    }
+}
+
+/// Per-second shard activity since the previous snapshot, only available while watching
+#[derive(Clone, Copy, Serialize)]
+pub struct ShardRates {
+    pub index_per_sec: f64,
+    pub query_per_sec: f64,
+    pub fetch_per_sec: f64,
+    pub write_load: f64,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct DocStats {
     count: u64,
     deleted: u64,
     total_size_in_bytes: Option<u64>,
 }
 
-#[derive(Deserialize, Serialize)]
+/// Store stats of a shard copy, where `reserved_in_bytes` is -1 when unknown and absent before
+/// Elasticsearch 7.9
+#[derive(Clone, Deserialize, Serialize)]
+pub struct StoreStats {
+    size_in_bytes: u64,
+    reserved_in_bytes: Option<i64>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct IndexingStats {
     index_total: u64,
     index_time_in_millis: u64,
@@ -50,7 +100,7 @@ pub struct IndexingStats {
     write_load: f64,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct SearchStats {
     open_contexts: u64,
     query_total: u64,
@@ -76,18 +126,13 @@ pub struct ShardRouting {
     state: String,
 }
 
-#[derive(Serialize)]
-struct DataStreamName {
-    r#type: &'static str,
-    dataset: &'static str,
-    namespace: &'static str,
-}
-
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 pub struct ShardDoc {
     data_stream: DataStreamName,
     #[serde(flatten)]
     enrich: ShardEnrich,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rates: Option<ShardRates>,
     shard: ShardData,
     stats: ShardStats,
     #[serde(rename = "@timestamp")]
@@ -112,6 +157,11 @@ impl ShardDoc {
             .unwrap_or_default()
     }
 
+    /// Whether this shard belongs to the write index of a data stream
+    pub fn is_write_index(&self) -> bool {
+        self.index().is_some_and(IndexSettings::is_write_index)
+    }
+
     pub fn shard_number(&self) -> u16 {
         self.shard.number
     }
@@ -120,12 +170,51 @@ impl ShardDoc {
         self.shard.routing.primary
     }
 
+    pub fn index(&self) -> Option<&IndexSettings> {
+        self.enrich.index.as_ref()
+    }
+
+    pub fn stats(&self) -> &ShardStats {
+        &self.stats
+    }
+
+    pub fn rates(&self) -> Option<&ShardRates> {
+        self.rates.as_ref()
+    }
+
+    pub fn set_rates(&mut self, rates: ShardRates) {
+        self.rates = Some(rates);
+    }
+
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    pub fn state(&self) -> &str {
+        &self.shard.routing.state
+    }
+
+    /// ID of the node the shard copy is allocated to
+    pub fn node_id(&self) -> &str {
+        &self.shard.routing.node
+    }
+
+    pub fn node_name(&self) -> Option<&str> {
+        self.enrich.node.as_ref().map(|n| n.name.as_str())
+    }
+
+    pub fn desired_node(&self) -> Option<&str> {
+        self.enrich.node.as_ref().and_then(|n| n.desired.as_deref())
+    }
+
     pub fn set_desired_node(&mut self, name: String) {
-        self.enrich.node.as_mut().map(|n| n.desired = Some(name));
+        if let Some(node) = self.enrich.node.as_mut() {
+            node.desired = Some(name);
+        }
     }
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 pub struct ShardData {
     number: u16,
     #[serde(flatten)]
@@ -139,31 +228,24 @@ pub struct ShardEnrich {
 }
 
 impl ShardDoc {
-    pub fn new(number: u16, stats: ShardStats, enrich: ShardEnrich) -> Self {
+    pub fn new(number: u16, stats: ShardStats, enrich: ShardEnrich, timestamp: i64) -> Self {
         ShardDoc {
-            data_stream: DataStreamName {
-                r#type: "metrics",
-                dataset: "shard",
-                namespace: "eshipster",
-            },
+            data_stream: DataStreamName::metrics("shards"),
             enrich,
+            rates: None,
             shard: ShardData {
                 number,
                 routing: stats.routing.clone(),
             },
             stats,
-            timestamp: *config::START_TIME,
+            timestamp,
         }
     }
+}
 
-    pub fn as_value(&self) -> serde_json::Value {
-        match serde_json::to_value(self) {
-            Ok(value) => value,
-            Err(e) => {
-                log::error!("Failed to serialize ShardDoc: {}", e);
-                serde_json::Value::Null
-            }
-        }
+impl ExportDoc for ShardDoc {
+    fn data_stream() -> &'static str {
+        "metrics-shards-eshipster"
     }
 }
 
@@ -0,0 +1,99 @@
+use super::{DataStreamName, ExportDoc};
+use serde::Serialize;
+
+/// Coefficients of variation across nodes, where 0 is a perfect balance
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct BalanceScore {
+    pub shards: f64,
+    pub bytes: f64,
+    pub write_load: f64,
+}
+
+impl BalanceScore {
+    /// Standard deviation divided by the mean, or 0 when there is nothing to balance
+    pub fn coefficient_of_variation(values: &[f64]) -> f64 {
+        if values.is_empty() {
+            return 0.0;
+        }
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        if mean <= 0.0 {
+            return 0.0;
+        }
+        let variance = values
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f64>()
+            / values.len() as f64;
+        variance.sqrt() / mean
+    }
+
+    /// Add another score multiplied by a weight, for averaging scores
+    pub fn add_weighted(&mut self, other: &BalanceScore, weight: f64) {
+        self.shards += other.shards * weight;
+        self.bytes += other.bytes * weight;
+        self.write_load += other.write_load * weight;
+    }
+
+    pub fn scaled(self, factor: f64) -> Self {
+        Self {
+            shards: self.shards * factor,
+            bytes: self.bytes * factor,
+            write_load: self.write_load * factor,
+        }
+    }
+}
+
+/// One document per evaluation, summarizing the balance before and after the plan
+#[derive(Serialize)]
+pub struct SummaryDoc {
+    data_stream: DataStreamName,
+    #[serde(rename = "@timestamp")]
+    timestamp: i64,
+    strategy: String,
+    nodes: usize,
+    shards: usize,
+    moves: usize,
+    relocating_bytes: u64,
+    before: BalanceScore,
+    after: BalanceScore,
+}
+
+impl SummaryDoc {
+    pub fn new(timestamp: i64, strategy: String) -> Self {
+        Self {
+            data_stream: DataStreamName::metrics("summary"),
+            timestamp,
+            strategy,
+            nodes: 0,
+            shards: 0,
+            moves: 0,
+            relocating_bytes: 0,
+            before: BalanceScore::default(),
+            after: BalanceScore::default(),
+        }
+    }
+
+    pub fn with_scores(self, nodes: usize, before: BalanceScore, after: BalanceScore) -> Self {
+        Self {
+            nodes,
+            before,
+            after,
+            ..self
+        }
+    }
+
+    pub fn with_moves(self, shards: usize, moves: usize, relocating_bytes: u64) -> Self {
+        Self {
+            shards,
+            moves,
+            relocating_bytes,
+            ..self
+        }
+    }
+}
+
+impl ExportDoc for SummaryDoc {
+    fn data_stream() -> &'static str {
+        "metrics-summary-eshipster"
+    }
+}
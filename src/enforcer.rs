@@ -0,0 +1,521 @@
+use crate::client::AuthType;
+use crate::data::{
+    ClusterSettings, Recoveries, Recovery, RerouteCommands, ShardDoc, ShardMove, ShardPlacements,
+};
+use crate::processor::format_bytes;
+use crate::receiver::{check_response, ElasticsearchReceiver, Receive};
+use clap::Args;
+use color_eyre::eyre::{eyre, Result};
+use elasticsearch::indices::IndicesPutSettingsParts;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Limits on how quickly planned moves are executed
+#[derive(Args, Clone)]
+pub struct ThrottleOptions {
+    /// Maximum number of shard relocations in flight at once
+    #[arg(
+        default_value_t = 2,
+        help = "Maximum concurrent shard relocations",
+        long
+    )]
+    pub concurrent_moves: usize,
+    /// Seconds between recovery progress checks
+    #[arg(
+        default_value_t = 10,
+        help = "Seconds between relocation progress checks",
+        long
+    )]
+    pub poll_interval: u64,
+}
+
+impl Default for ThrottleOptions {
+    fn default() -> Self {
+        Self {
+            concurrent_moves: 2,
+            poll_interval: 10,
+        }
+    }
+}
+
+/// Applies a shard balance plan to an Elasticsearch cluster
+pub struct Enforcer {
+    pin: bool,
+    receiver: ElasticsearchReceiver,
+    shutdown: Option<watch::Receiver<bool>>,
+    throttle: ThrottleOptions,
+}
+
+/// Where an in-flight move stands after a progress check
+enum Progress {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Exclusion filter on a node attribute no node has, which never affects allocation but marks
+/// the indices eshipster pinned
+const PIN_MARKER: &str = "index.routing.allocation.exclude.eshipster_pin";
+
+/// Consecutive failed progress checks before the in-flight relocations are given up on
+const MAX_PROGRESS_FAILURES: usize = 5;
+
+impl Enforcer {
+    /// Create a new Enforcer on the connection of an Elasticsearch receiver
+    pub fn new(receiver: ElasticsearchReceiver) -> Self {
+        Self {
+            pin: false,
+            receiver,
+            shutdown: None,
+            throttle: ThrottleOptions::default(),
+        }
+    }
+
+    /// Pin data stream write indices to their planned nodes with allocation filters
+    pub fn pin(self, pin: bool) -> Self {
+        Self { pin, ..self }
+    }
+
+    /// Stop scheduling new relocations once the receiver flips to true
+    pub fn shutdown(self, shutdown: watch::Receiver<bool>) -> Self {
+        Self {
+            shutdown: Some(shutdown),
+            ..self
+        }
+    }
+
+    /// Limit how many relocations run at once and how often progress is checked
+    pub fn throttle(self, throttle: ThrottleOptions) -> Self {
+        Self { throttle, ..self }
+    }
+
+    pub fn parse(host: &str, auth_type: &AuthType) -> Result<Self> {
+        log::debug!("Parsing enforcer: {}", host);
+        match ElasticsearchReceiver::parse(host, auth_type) {
+            Some(receiver) => Ok(Self::new(receiver?)),
+            None => Err(eyre!("Can only enforce balance on an Elasticsearch host")),
+        }
+    }
+
+    /// Execute the planned moves, pinning managed indices to their planned nodes when enabled.
+    /// Returns the number of relocations that completed.
+    pub async fn enforce(&self, shards: &[ShardDoc], moves: &[ShardMove]) -> Result<usize> {
+        // The filter each index has after a pass, so the next pass diffs against what was
+        // written rather than the settings read before any of them
+        let mut written = BTreeMap::new();
+        if !self.pin {
+            // Pins left by an earlier run with pinning on are cleared, as nothing manages them
+            self.update_pins(shards, &mut written, |_| Vec::new())
+                .await?;
+            return Ok(self.reroute(moves).await?.len());
+        }
+        // Widen the pins to cover both placements so the filters never block a planned move,
+        // then narrow them to wherever each shard ended up once the moves are done
+        self.update_pins(shards, &mut written, |shard| {
+            shard
+                .desired_node()
+                .into_iter()
+                .chain(shard.node_name())
+                .collect()
+        })
+        .await?;
+        let moved = self.reroute(moves).await?;
+        self.update_pins(shards, &mut written, |shard| {
+            let planned = moves.iter().find(|shard_move| shard_move.is_move_of(shard));
+            match planned {
+                Some(shard_move) if !moved.contains(&shard_move) => {
+                    shard.node_name().into_iter().collect()
+                }
+                _ => shard.desired_node().into_iter().collect(),
+            }
+        })
+        .await?;
+        Ok(moved.len())
+    }
+
+    /// Execute the moves a few at a time, waiting for each relocation to finish before
+    /// scheduling more. Returns the moves that completed.
+    async fn reroute<'m>(&self, moves: &'m [ShardMove]) -> Result<Vec<&'m ShardMove>> {
+        let total = moves.len();
+        if total == 0 {
+            return Ok(Vec::new());
+        }
+        let (incoming_limit, outgoing_limit) = self.node_recovery_limits().await;
+        let concurrent = self.throttle.concurrent_moves.max(1);
+        log::info!(
+            "Rerouting {total} shards on {}, {concurrent} at a time with {incoming_limit} incoming \
+             and {outgoing_limit} outgoing per node",
+            self.receiver
+        );
+        let mut recoveries = match self.receiver.get::<Recoveries>().await {
+            Ok(recoveries) => recoveries,
+            Err(e) => {
+                log::warn!("Failed to read active recoveries, assuming there are none: {e}");
+                Vec::new()
+            }
+        };
+
+        let mut pending: VecDeque<&ShardMove> = moves.iter().collect();
+        let mut in_flight: Vec<&ShardMove> = Vec::new();
+        let mut completed = Vec::new();
+        let (mut failed, mut bytes) = (0, 0);
+        let mut failures = 0;
+
+        while !pending.is_empty() || !in_flight.is_empty() {
+            // Relocations already started finish on their own, so shutting down only stops new
+            // ones from being scheduled and waits for the rest to be accounted for
+            if self.is_shutting_down() && !pending.is_empty() {
+                log::warn!(
+                    "Shutting down, skipping {} pending moves and waiting for {} in flight",
+                    pending.len(),
+                    in_flight.len()
+                );
+                pending.clear();
+            }
+            // Schedule whatever fits within the global and per-node limits, which recoveries the
+            // cluster started itself count towards as well
+            let (mut incoming, mut outgoing) = node_recoveries(&recoveries, &in_flight);
+            let mut deferred = VecDeque::new();
+            while let Some(shard_move) = pending.pop_front() {
+                if in_flight.len() >= concurrent
+                    || incoming.get(&shard_move.to_node).copied().unwrap_or(0) >= incoming_limit
+                    || outgoing.get(&shard_move.from_node).copied().unwrap_or(0) >= outgoing_limit
+                {
+                    deferred.push_back(shard_move);
+                    continue;
+                }
+                match self.move_shard(shard_move).await {
+                    Ok(()) => {
+                        log::info!("Started {shard_move}");
+                        *incoming.entry(shard_move.to_node.clone()).or_insert(0) += 1;
+                        *outgoing.entry(shard_move.from_node.clone()).or_insert(0) += 1;
+                        in_flight.push(shard_move);
+                    }
+                    Err(e) => {
+                        log::error!("Failed to move {shard_move}: {e}");
+                        failed += 1;
+                    }
+                }
+            }
+            pending = deferred;
+
+            if in_flight.is_empty() && pending.is_empty() {
+                continue;
+            }
+            tokio::time::sleep(Duration::from_secs(self.throttle.poll_interval)).await;
+
+            let progress = match self.progress(&in_flight).await {
+                Ok((progress, active)) => {
+                    recoveries = active;
+                    progress
+                }
+                Err(e) => {
+                    failures += 1;
+                    if failures >= MAX_PROGRESS_FAILURES {
+                        return Err(eyre!(
+                            "Lost track of {} relocations after {failures} failed progress checks: {e}",
+                            in_flight.len()
+                        ));
+                    }
+                    log::warn!("Failed to check relocation progress: {e}");
+                    continue;
+                }
+            };
+            failures = 0;
+            let mut running = Vec::new();
+            for (shard_move, progress) in in_flight.into_iter().zip(progress) {
+                match progress {
+                    Progress::Running => running.push(shard_move),
+                    Progress::Completed => {
+                        log::info!("Finished {shard_move}");
+                        bytes += shard_move.bytes;
+                        completed.push(shard_move);
+                    }
+                    Progress::Failed => {
+                        log::error!(
+                            "Stopped {shard_move} without reaching {}",
+                            shard_move.to_node
+                        );
+                        failed += 1;
+                    }
+                }
+            }
+            in_flight = running;
+            log::info!(
+                "Relocated {}/{total} shards ({}), {} in flight, {} pending, {failed} failed",
+                completed.len(),
+                format_bytes(bytes),
+                in_flight.len(),
+                pending.len()
+            );
+        }
+        Ok(completed)
+    }
+
+    /// Where each in-flight move stands, along with the recoveries now active. Moves no longer
+    /// recovering are only complete once their shard copy is started on the target node.
+    async fn progress(&self, in_flight: &[&ShardMove]) -> Result<(Vec<Progress>, Recoveries)> {
+        let recoveries = self.receiver.get::<Recoveries>().await?;
+        let recovering: Vec<bool> = in_flight
+            .iter()
+            .map(
+                |shard_move| match recoveries.iter().find(|r| r.relocates(shard_move)) {
+                    Some(recovery) => {
+                        log::debug!("Relocating {shard_move} in stage {}", recovery.stage);
+                        true
+                    }
+                    None => false,
+                },
+            )
+            .collect();
+        if recovering.iter().all(|recovering| *recovering) {
+            let progress = in_flight.iter().map(|_| Progress::Running).collect();
+            return Ok((progress, recoveries));
+        }
+
+        let placements = self.receiver.get::<ShardPlacements>().await?;
+        let progress = in_flight
+            .iter()
+            .zip(recovering)
+            .map(|(shard_move, recovering)| {
+                if recovering {
+                    return Progress::Running;
+                }
+                let copies = placements.iter().filter(|copy| copy.is_copy_of(shard_move));
+                let (from, to) = (
+                    Some(shard_move.from_node.as_str()),
+                    Some(shard_move.to_node.as_str()),
+                );
+                let mut progress = Progress::Failed;
+                for copy in copies {
+                    match (copy.node_name(), copy.state.as_str()) {
+                        (node, "STARTED") if node == to => return Progress::Completed,
+                        (node, "INITIALIZING") if node == to => progress = Progress::Running,
+                        (node, "RELOCATING") if node == from => progress = Progress::Running,
+                        _ => {}
+                    }
+                }
+                progress
+            });
+        Ok((progress.collect(), recoveries))
+    }
+
+    fn is_shutting_down(&self) -> bool {
+        self.shutdown
+            .as_ref()
+            .is_some_and(|shutdown| *shutdown.borrow())
+    }
+
+    /// Incoming and outgoing recoveries allowed per node, defaulting to Elasticsearch's 2
+    async fn node_recovery_limits(&self) -> (usize, usize) {
+        let settings = match self.receiver.get::<ClusterSettings>().await {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::warn!("Failed to read recovery limits, using defaults: {e}");
+                ClusterSettings::default()
+            }
+        };
+        let prefix = "cluster.routing.allocation";
+        let both = settings
+            .get_i64(&format!("{prefix}.node_concurrent_recoveries"))
+            .unwrap_or(2);
+        let limit = |direction: &str| {
+            settings
+                .get_i64(&format!("{prefix}.node_concurrent_{direction}_recoveries"))
+                .unwrap_or(both)
+                .max(1) as usize
+        };
+        (limit("incoming"), limit("outgoing"))
+    }
+
+    /// Set `index.routing.allocation.include._name` on each data stream write index to the given
+    /// nodes of its shards, and clear the pins eshipster set on indices it no longer manages, such
+    /// as backing indices that have since rolled over, or every index when pinning is off. Filters
+    /// set by hand are left alone. `written` holds the filters set by earlier passes, which take
+    /// the place of the index settings read before them. Returns the number of indices updated.
+    async fn update_pins<F>(
+        &self,
+        shards: &[ShardDoc],
+        written: &mut BTreeMap<String, Vec<String>>,
+        nodes_of: F,
+    ) -> Result<usize>
+    where
+        F: Fn(&ShardDoc) -> Vec<&str>,
+    {
+        let mut pins: BTreeMap<String, Option<BTreeSet<String>>> = BTreeMap::new();
+        let mut unassigned: BTreeSet<String> = BTreeSet::new();
+        let mut pinned: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for shard in shards {
+            let Some(index) = shard.index() else {
+                continue;
+            };
+            let index_name = shard.index_name();
+            // eshipster always writes the marker along with a pin and clears both together
+            let (current, marked) = match written.get(&index_name) {
+                Some(nodes) => (nodes.clone(), !nodes.is_empty()),
+                None => (index.pinned_nodes(), index.has_pin_marker()),
+            };
+            if !self.pin || !index.is_write_index() {
+                // Rolled over, left its data stream or pinning is off, so nothing manages it now
+                if marked {
+                    pins.insert(index_name.clone(), None);
+                    pinned.insert(index_name, current);
+                }
+                continue;
+            }
+            if !current.is_empty() && !marked {
+                log::debug!("Keeping the allocation filter set on {index_name} by hand");
+                continue;
+            }
+            pinned.insert(index_name.clone(), current);
+            let nodes = nodes_of(shard);
+            if nodes.is_empty() {
+                unassigned.insert(index_name);
+                continue;
+            }
+            pins.entry(index_name)
+                .or_insert_with(|| Some(BTreeSet::new()))
+                .get_or_insert_with(BTreeSet::new)
+                .extend(nodes.into_iter().map(String::from));
+        }
+
+        let mut updated = 0;
+        for (index, nodes) in pins {
+            // An index with unplaced shards would be stuck by a pin that leaves them nowhere to go
+            if unassigned.contains(&index) {
+                continue;
+            }
+            let current = pinned.remove(&index).unwrap_or_default();
+            let nodes = match nodes {
+                Some(nodes) if nodes.iter().eq(current.iter().collect::<BTreeSet<_>>()) => continue,
+                Some(nodes) => nodes.into_iter().collect(),
+                None => Vec::new(),
+            };
+            let filter = (!nodes.is_empty()).then(|| nodes.join(","));
+            match self.pin_index(&index, filter.as_deref()).await {
+                Ok(()) => {
+                    match &filter {
+                        Some(filter) => log::info!("Pinned {index} to {filter}"),
+                        None => log::info!("Unpinned {index}"),
+                    }
+                    written.insert(index, nodes);
+                    updated += 1;
+                }
+                Err(e) => log::error!("Failed to update the pin of {index}: {e}"),
+            }
+        }
+        Ok(updated)
+    }
+
+    /// Set or clear the node filter of an index, along with the marker that tells eshipster's
+    /// pins apart from filters set by hand
+    async fn pin_index(&self, index: &str, nodes: Option<&str>) -> Result<()> {
+        let marker = nodes.map(|_| "true");
+        let response = self
+            .receiver
+            .client()
+            .indices()
+            .put_settings(IndicesPutSettingsParts::Index(&[index]))
+            .body(json!({
+                "index.routing.allocation.include._name": nodes,
+                PIN_MARKER: marker,
+            }))
+            .send()
+            .await?;
+        check_response(response).await?;
+        Ok(())
+    }
+
+    async fn move_shard(&self, shard_move: &ShardMove) -> Result<()> {
+        let commands = RerouteCommands::from(shard_move);
+        let response = self
+            .receiver
+            .client()
+            .cluster()
+            .reroute()
+            .metric(&["none"])
+            .body(commands)
+            .send()
+            .await?;
+        check_response(response).await?;
+        Ok(())
+    }
+}
+
+/// Incoming and outgoing peer recoveries of each node, both the moves in flight and any others
+/// the cluster is running, such as replicas being rebuilt after a node left
+fn node_recoveries(
+    recoveries: &[Recovery],
+    in_flight: &[&ShardMove],
+) -> (HashMap<String, usize>, HashMap<String, usize>) {
+    let (mut incoming, mut outgoing) = (HashMap::new(), HashMap::new());
+    for shard_move in in_flight {
+        *incoming.entry(shard_move.to_node.clone()).or_insert(0) += 1;
+        *outgoing.entry(shard_move.from_node.clone()).or_insert(0) += 1;
+    }
+    let others = recoveries.iter().filter(|recovery| {
+        recovery.r#type == "peer" && !in_flight.iter().any(|m| recovery.relocates(m))
+    });
+    for recovery in others {
+        if let Some(target) = &recovery.target_node {
+            *incoming.entry(target.clone()).or_insert(0) += 1;
+        }
+        if let Some(source) = &recovery.source_node {
+            *outgoing.entry(source.clone()).or_insert(0) += 1;
+        }
+    }
+    (incoming, outgoing)
+}
+
+impl std::fmt::Display for Enforcer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shard_move(shard: u16, from: &str, to: &str) -> ShardMove {
+        ShardMove {
+            index: "logs".to_string(),
+            shard,
+            primary: true,
+            bytes: 0,
+            from_node: from.to_string(),
+            to_node: to.to_string(),
+        }
+    }
+
+    fn recovery(shard: u16, r#type: &str, source: Option<&str>, target: &str) -> Recovery {
+        Recovery {
+            index: "logs".to_string(),
+            shard: shard.to_string(),
+            stage: "index".to_string(),
+            source_node: source.map(String::from),
+            target_node: Some(target.to_string()),
+            r#type: r#type.to_string(),
+        }
+    }
+
+    #[test]
+    fn node_recoveries_count_moves_and_other_peer_recoveries_once() {
+        let moving = shard_move(0, "hot-1", "hot-2");
+        let recoveries = vec![
+            // The move itself shows up as a recovery and must not be counted twice
+            recovery(0, "peer", Some("hot-1"), "hot-2"),
+            // A replica the cluster rebuilds on its own
+            recovery(1, "peer", Some("hot-3"), "hot-2"),
+            // Local recoveries do not copy between nodes
+            recovery(2, "existing_store", None, "hot-1"),
+        ];
+        let (incoming, outgoing) = node_recoveries(&recoveries, &[&moving]);
+        assert_eq!(incoming.get("hot-2"), Some(&2));
+        assert_eq!(incoming.get("hot-1"), None);
+        assert_eq!(outgoing.get("hot-1"), Some(&1));
+        assert_eq!(outgoing.get("hot-3"), Some(&1));
+    }
+}
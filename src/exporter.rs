@@ -4,8 +4,9 @@ mod stream;
 
 use crate::client::{Auth, AuthType, Host};
 use crate::config;
-use crate::data::ShardDoc;
-use color_eyre::eyre::Result;
+use crate::data::ExportDoc;
+use crate::receiver::ElasticsearchReceiver;
+use color_eyre::eyre::{eyre, Result};
 use elasticsearch::ElasticsearchExporter;
 use file::FileExporter;
 use std::path::Path;
@@ -13,7 +14,7 @@ use stream::StreamExporter;
 use url::Url;
 
 trait Export {
-    async fn write(&self, docs: Vec<ShardDoc>) -> Result<usize>;
+    async fn write<T: ExportDoc>(&self, docs: impl IntoIterator<Item = T>) -> Result<usize>;
     async fn is_connected(&self) -> bool;
 }
 
@@ -70,7 +71,19 @@ impl Exporter {
         Ok(Self::File(exporter))
     }
 
-    pub async fn write(&self, docs: Vec<ShardDoc>) -> Result<usize> {
+    /// Export back to the Elasticsearch host that is being read, using the receiver's
+    /// `ESHIPSTER_RC_*` credentials rather than the output's
+    pub fn to_receiver(host: &str, auth_type: &AuthType) -> Result<Self> {
+        log::debug!("Parsing exporter for receiver: {}", host);
+        match ElasticsearchReceiver::parse(host, auth_type) {
+            Some(receiver) => Ok(Self::Elasticsearch(ElasticsearchExporter::from_receiver(
+                &receiver?,
+            ))),
+            None => Err(eyre!("Host must be a known host or a URL")),
+        }
+    }
+
+    pub async fn write<T: ExportDoc>(&self, docs: impl IntoIterator<Item = T>) -> Result<usize> {
         match self {
             Self::Elasticsearch(exporter) => exporter.write(docs).await,
             Self::File(exporter) => exporter.write(docs).await,
@@ -1,14 +1,24 @@
 use super::Export;
 use crate::client::{Auth, ElasticsearchBuilder, Host};
-use crate::data::ShardDoc;
-use color_eyre::eyre::Result;
+use crate::config;
+use crate::data::ExportDoc;
+use crate::receiver::ElasticsearchReceiver;
+use color_eyre::eyre::{eyre, Result};
 use elasticsearch::{
     http::{headers, request::JsonBody, response::Response, Method},
     BulkOperation, BulkParts, Elasticsearch,
 };
+use futures::stream::{self, StreamExt};
+use serde::Deserialize;
 use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
 use url::Url;
 
+/// Retries of documents rejected with a 429 or 5xx status, with the delay doubling each time
+const MAX_RETRIES: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
 pub struct ElasticsearchExporter {
     client: Elasticsearch,
     url: Url,
@@ -32,6 +42,14 @@ impl ElasticsearchExporter {
         Ok(Self { client, url })
     }
 
+    /// Write back to the cluster a receiver reads from, over the same connection
+    pub fn from_receiver(receiver: &ElasticsearchReceiver) -> Self {
+        Self {
+            client: receiver.client().clone(),
+            url: receiver.url().clone(),
+        }
+    }
+
     /// Send a request to an arbitrary path on the Elasticsearch client
     pub async fn send(&self, method: &str, path: &str, value: Option<&Value>) -> Result<Response> {
         let method = match method {
@@ -40,10 +58,7 @@ impl ElasticsearchExporter {
             "DELETE" => Method::Delete,
             _ => Method::Get,
         };
-        let body = match value {
-            Some(value) => Some(JsonBody::new(value)),
-            None => None,
-        };
+        let body = value.map(JsonBody::new);
         self.client
             .send(
                 method,
@@ -58,25 +73,130 @@ impl ElasticsearchExporter {
     }
 }
 
-impl Export for ElasticsearchExporter {
-    async fn write(&self, docs: Vec<ShardDoc>) -> Result<usize> {
-        let index = "metrics-shards-eshipster";
-        let doc_count = docs.len();
-        let ops: Vec<BulkOperation<serde_json::Value>> = docs
-            .into_iter()
-            .map(|doc| BulkOperation::create(doc.as_value()).into())
+impl ElasticsearchExporter {
+    /// Send one bulk request, returning the status and any error reason of each document in
+    /// order. A rejected request is reported as the same status for every document.
+    async fn bulk(&self, index: &str, docs: &[Value]) -> Result<Vec<(u16, Option<String>)>> {
+        let ops: Vec<BulkOperation<Value>> = docs
+            .iter()
+            .map(|doc| BulkOperation::create(doc.clone()).into())
             .collect();
 
         let response = self
             .client
-            .bulk(BulkParts::Index(&index))
+            .bulk(BulkParts::Index(index))
             .body(ops)
             .send()
             .await?;
 
-        let body = response.text().await?;
-        log::trace!("{}", body);
-        Ok(doc_count)
+        let status = response.status_code().as_u16();
+        if !response.status_code().is_success() {
+            let body = response.text().await?;
+            log::trace!("{}", body);
+            return match is_retryable(status) {
+                true => Ok(vec![(status, Some(body)); docs.len()]),
+                false => Err(eyre!("Bulk request failed with {status}: {body}")),
+            };
+        }
+
+        let body = response.json::<BulkResponse>().await?;
+        if body.items.len() != docs.len() {
+            return Err(eyre!(
+                "Bulk response has {} items for {} docs",
+                body.items.len(),
+                docs.len()
+            ));
+        }
+        Ok(body
+            .items
+            .into_iter()
+            .map(|item| {
+                // Each item is keyed by its operation, e.g. `create`
+                let result = item.into_values().next().unwrap_or_default();
+                let reason = result
+                    .error
+                    .map(|error| format!("{}: {}", error.r#type, error.reason.unwrap_or_default()));
+                (result.status, reason)
+            })
+            .collect())
+    }
+}
+
+impl ElasticsearchExporter {
+    /// Index one batch, retrying rejected documents. Returns the number indexed.
+    async fn write_batch(&self, index: &str, mut pending: Vec<Value>) -> Result<usize> {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut indexed = 0;
+        let mut failures: BTreeMap<String, usize> = BTreeMap::new();
+
+        for attempt in 0..=MAX_RETRIES {
+            let results = self.bulk(index, &pending).await?;
+            let mut retry = Vec::new();
+            for (doc, (status, reason)) in pending.into_iter().zip(results) {
+                match status {
+                    200..=299 => indexed += 1,
+                    status if is_retryable(status) && attempt < MAX_RETRIES => retry.push(doc),
+                    status => {
+                        let reason = reason.unwrap_or_else(|| format!("status {status}"));
+                        *failures.entry(reason).or_default() += 1;
+                    }
+                }
+            }
+            if retry.is_empty() {
+                break;
+            }
+            log::warn!(
+                "Retrying {} docs to {index} in {}s",
+                retry.len(),
+                backoff.as_secs()
+            );
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+            pending = retry;
+        }
+
+        for (reason, count) in &failures {
+            log::error!("Failed to index {count} docs to {index}: {reason}");
+        }
+        Ok(indexed)
+    }
+}
+
+impl Export for ElasticsearchExporter {
+    async fn write<T: ExportDoc>(&self, docs: impl IntoIterator<Item = T>) -> Result<usize> {
+        let index = T::data_stream();
+        let (max_docs, max_bytes) = (*config::ESHIPSTER_BULK_DOCS, *config::ESHIPSTER_BULK_BYTES);
+        let concurrency = *config::ESHIPSTER_BULK_CONCURRENCY;
+
+        // Docs are serialized as batches are taken, so only the batches in flight are held as JSON
+        let docs = docs.into_iter().map(|doc| doc.as_value());
+        let batches = batches(docs, max_docs, max_bytes);
+
+        let results: Vec<Result<usize>> = stream::iter(batches)
+            .map(|batch| {
+                log::debug!("Sending batch of {} docs to {index}", batch.len());
+                self.write_batch(index, batch)
+            })
+            .buffer_unordered(concurrency)
+            .collect()
+            .await;
+
+        // Batches that succeeded are indexed regardless, so count them before reporting a failure
+        let batches = results.len();
+        let (mut indexed, mut errors) = (0, Vec::new());
+        for result in results {
+            match result {
+                Ok(count) => indexed += count,
+                Err(e) => errors.push(e),
+            }
+        }
+        let failed = errors.len();
+        match errors.into_iter().next() {
+            None => Ok(indexed),
+            Some(e) => Err(e.wrap_err(format!(
+                "{failed} of {batches} batches to {index} failed, {indexed} docs were indexed"
+            ))),
+        }
     }
 
     async fn is_connected(&self) -> bool {
@@ -111,3 +231,100 @@ impl std::fmt::Display for ElasticsearchExporter {
         write!(f, "{}", self.url)
     }
 }
+
+/// The action line sent ahead of every document in a bulk request
+const CREATE_ACTION: &str = r#"{"create":{}}"#;
+
+/// Split docs into batches of at most `max_docs` documents and `max_bytes` of bulk request body,
+/// counting each document's action line and newlines. A document larger than `max_bytes` on its
+/// own is still sent, in a batch by itself.
+fn batches(
+    docs: impl Iterator<Item = Value>,
+    max_docs: usize,
+    max_bytes: usize,
+) -> impl Iterator<Item = Vec<Value>> {
+    let mut docs = docs
+        .map(|value| {
+            let size = CREATE_ACTION.len() + value.to_string().len() + 2;
+            (value, size)
+        })
+        .peekable();
+    std::iter::from_fn(move || {
+        let (mut batch, mut bytes) = (Vec::new(), 0);
+        while let Some((_, size)) = docs.peek() {
+            if !batch.is_empty() && (batch.len() >= max_docs || bytes + size > max_bytes) {
+                break;
+            }
+            bytes += size;
+            batch.extend(docs.next().map(|(value, _)| value));
+        }
+        (!batch.is_empty()).then_some(batch)
+    })
+}
+
+/// Too many requests and server errors are transient, anything else will fail again
+fn is_retryable(status: u16) -> bool {
+    status == 429 || status >= 500
+}
+
+#[derive(Deserialize)]
+struct BulkResponse {
+    items: Vec<HashMap<String, BulkItem>>,
+}
+
+#[derive(Default, Deserialize)]
+struct BulkItem {
+    status: u16,
+    error: Option<BulkError>,
+}
+
+#[derive(Deserialize)]
+struct BulkError {
+    r#type: String,
+    reason: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Bulk request bytes a doc takes, with its action line and both newlines
+    fn doc_size(doc: &Value) -> usize {
+        format!("{CREATE_ACTION}\n{doc}\n").len()
+    }
+
+    fn sizes(batches: impl Iterator<Item = Vec<Value>>) -> Vec<usize> {
+        batches.map(|batch| batch.len()).collect()
+    }
+
+    #[test]
+    fn batches_split_on_doc_count() {
+        let docs = (0..5).map(|i| json!({ "i": i }));
+        assert_eq!(sizes(batches(docs, 2, usize::MAX)), vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn batches_count_action_lines_towards_byte_limit() {
+        let doc = json!({ "i": 0 });
+        let docs = std::iter::repeat_n(doc.clone(), 4);
+        // Two docs fit only when the action lines are counted exactly, not one byte less
+        let max_bytes = 2 * doc_size(&doc);
+        assert_eq!(sizes(batches(docs.clone(), 10, max_bytes)), vec![2, 2]);
+        assert_eq!(sizes(batches(docs, 10, max_bytes - 1)), vec![1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn batches_send_an_oversized_doc_alone() {
+        let docs = vec![
+            json!({ "i": 0 }),
+            json!({ "i": "x".repeat(100) }),
+            json!({ "i": 2 }),
+        ];
+        let max_bytes = doc_size(&docs[0]) * 2;
+        assert_eq!(
+            sizes(batches(docs.into_iter(), 10, max_bytes)),
+            vec![1, 1, 1]
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use super::Export;
-use crate::data::ShardDoc;
+use crate::data::ExportDoc;
 use color_eyre::eyre::Result;
 use std::{
     fs::{File, OpenOptions},
@@ -31,7 +31,7 @@ impl Export for FileExporter {
         is_file
     }
 
-    async fn write(&self, docs: Vec<ShardDoc>) -> Result<usize> {
+    async fn write<T: ExportDoc>(&self, docs: impl IntoIterator<Item = T>) -> Result<usize> {
         log::debug!("Writing docs to file {}", &self.path.display());
         let mut writer = BufWriter::new(&self.file);
         let mut doc_count = 0;
@@ -1,5 +1,5 @@
 use super::Export;
-use crate::data::ShardDoc;
+use crate::data::ExportDoc;
 use color_eyre::eyre::Result;
 
 pub struct StreamExporter {}
@@ -11,12 +11,13 @@ impl StreamExporter {
 }
 
 impl Export for StreamExporter {
-    async fn write(&self, docs: Vec<ShardDoc>) -> Result<usize> {
-        log::debug!("Writing {} docs to stdout", docs.len());
-        let doc_count = docs.len();
+    async fn write<T: ExportDoc>(&self, docs: impl IntoIterator<Item = T>) -> Result<usize> {
+        log::debug!("Writing docs to stdout");
+        let mut doc_count = 0;
         for doc in docs {
             serde_json::to_writer(std::io::stdout(), &doc)?;
             println!();
+            doc_count += 1;
         }
         Ok(doc_count)
     }
@@ -1,14 +1,24 @@
 mod client;
 mod config;
 mod data;
+mod enforcer;
 mod exporter;
 mod processor;
 mod receiver;
+mod watcher;
 
-use clap::{Parser, Subcommand};
-use client::AuthType;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand};
+use client::setup::Lifecycle;
+use client::{AuthType, Host, KibanaClient};
+use enforcer::{Enforcer, ThrottleOptions};
 use exporter::Exporter;
+use processor::{BalanceOptions, Plan};
 use receiver::Receiver;
+use std::path::PathBuf;
+use std::time::Duration;
+use url::Url;
+use watcher::Watcher;
 
 // Define command line arguments
 #[derive(Parser)]
@@ -32,6 +42,49 @@ enum Commands {
         /// An alternative output to send the shard documents to
         #[arg(help = "An alternative output to send the shard documents to")]
         output: Option<String>,
+        /// Authentication method to use (none, basic, apikey, etc.)
+        #[arg(
+            default_value = "none",
+            help = "Authentication method",
+            long,
+            value_enum
+        )]
+        auth: AuthType,
+        /// Authentication method to use (none, basic, apikey, etc.)
+        #[arg(
+            default_value = "none",
+            help = "Authentication method",
+            long,
+            value_enum
+        )]
+        output_auth: AuthType,
+        /// Print the plan without executing any shard moves
+        #[arg(help = "Print the plan without moving shards", long)]
+        dry_run: bool,
+        /// Pin data stream write indices to their planned nodes with allocation filters
+        #[arg(help = "Pin write indices to their planned nodes", long)]
+        pin: bool,
+        #[command(flatten)]
+        options: BalanceOptions,
+        #[command(flatten)]
+        throttle: ThrottleOptions,
+    },
+    /// Record the cluster APIs into a directory that can be evaluated offline
+    Capture {
+        /// The host to record the APIs of
+        #[arg(help = "The host to capture")]
+        host: String,
+        /// The directory to write the API responses to
+        #[arg(help = "The directory to write the API responses to")]
+        dir: PathBuf,
+        /// Authentication method to use (none, basic, apikey, etc.)
+        #[arg(
+            default_value = "none",
+            help = "Authentication method",
+            long,
+            value_enum
+        )]
+        auth: AuthType,
     },
     /// Collect shard stats and calculate the ideal shard balance
     Eval {
@@ -57,6 +110,63 @@ enum Commands {
             value_enum
         )]
         output_auth: AuthType,
+        #[command(flatten)]
+        options: BalanceOptions,
+    },
+    /// Recommend primary shard counts that spread data stream write indices evenly
+    Recommend {
+        /// The input to collect data stream, index and node settings from
+        #[arg(help = "The input to collect settings from")]
+        input: String,
+        /// Update each index template with its recommended shard count
+        #[arg(help = "Apply the recommendations to the index templates", long)]
+        apply: bool,
+        /// Authentication method to use (none, basic, apikey, etc.)
+        #[arg(
+            default_value = "none",
+            help = "Authentication method",
+            long,
+            value_enum
+        )]
+        auth: AuthType,
+    },
+    /// Evaluate a directory of captured snapshots in order, as if they were being watched
+    Replay {
+        /// Directory holding one captured snapshot per subdirectory
+        #[arg(help = "Directory of captured snapshots")]
+        input: String,
+        /// The output to send the shard documents to
+        #[arg(help = "The output to send the shard documents to")]
+        output: Option<String>,
+        /// Authentication method to use (none, basic, apikey, etc.)
+        #[arg(
+            default_value = "none",
+            help = "Authentication method",
+            long,
+            value_enum
+        )]
+        output_auth: AuthType,
+        #[command(flatten)]
+        options: BalanceOptions,
+    },
+    /// Apply the planned moves to an in-memory model and report the resulting balance
+    Simulate {
+        /// The input to collect shard stats from
+        #[arg(help = "The input to collect shard stats from")]
+        input: String,
+        /// Authentication method to use (none, basic, apikey, etc.)
+        #[arg(
+            default_value = "none",
+            help = "Authentication method",
+            long,
+            value_enum
+        )]
+        input_auth: AuthType,
+        /// Simulate every strategy and compare their results
+        #[arg(help = "Compare the results of every strategy", long)]
+        compare: bool,
+        #[command(flatten)]
+        options: BalanceOptions,
     },
     /// Setup Elasticsearch assets for visualizing output data
     Setup {
@@ -71,6 +181,22 @@ enum Commands {
             value_enum
         )]
         auth: AuthType,
+        /// Lifecycle that deletes old documents (ilm, dsl)
+        #[arg(default_value = "ilm", help = "Lifecycle management", long, value_enum)]
+        lifecycle: Lifecycle,
+        /// How long to keep documents, as an Elasticsearch time unit such as 30d
+        #[arg(default_value = "30d", help = "Document retention period", long)]
+        retention: String,
+        /// Kibana to import the data view and visualizations into
+        #[arg(help = "Kibana host to import saved objects into", long)]
+        kibana: Option<String>,
+        /// Replace saved objects that already exist in Kibana
+        #[arg(
+            help = "Overwrite existing Kibana saved objects",
+            long,
+            requires = "kibana"
+        )]
+        overwrite: bool,
     },
     /// Continuously monitor and enforce shard balance on an Elasticsearch cluster
     Watch {
@@ -80,6 +206,39 @@ enum Commands {
         /// An alternative output to send the shard documents to
         #[arg(help = "An alternative output to send the shard documents to")]
         output: Option<String>,
+        /// Seconds to wait between evaluation cycles
+        #[arg(default_value_t = 60, help = "Seconds between evaluation cycles", long)]
+        interval: u64,
+        /// Execute the planned shard moves on every cycle
+        #[arg(help = "Execute the planned shard moves on every cycle", long)]
+        enforce: bool,
+        /// Pin data stream write indices to their planned nodes with allocation filters
+        #[arg(
+            help = "Pin write indices to their planned nodes",
+            long,
+            requires = "enforce"
+        )]
+        pin: bool,
+        /// Authentication method to use (none, basic, apikey, etc.)
+        #[arg(
+            default_value = "none",
+            help = "Authentication method",
+            long,
+            value_enum
+        )]
+        auth: AuthType,
+        /// Authentication method to use (none, basic, apikey, etc.)
+        #[arg(
+            default_value = "none",
+            help = "Authentication method",
+            long,
+            value_enum
+        )]
+        output_auth: AuthType,
+        #[command(flatten)]
+        options: BalanceOptions,
+        #[command(flatten)]
+        throttle: ThrottleOptions,
     },
 }
 
@@ -100,29 +259,104 @@ async fn main() {
 
     // Use clap to parse command line arguments
     let cli = Cli::parse();
+    if let Commands::Recommend {
+        input, apply: true, ..
+    } = &cli.command
+    {
+        // Templates can only be updated on a cluster, not a diagnostic bundle or capture
+        if Host::parse(input).is_none() && Url::parse(input).is_err() {
+            let mut command = Cli::command();
+            command.build();
+            command
+                .find_subcommand_mut("recommend")
+                .expect("recommend is a subcommand")
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--apply requires an Elasticsearch host or URL as the input",
+                )
+                .exit();
+        }
+    }
 
     match &cli.command {
-        Commands::Balance { host, output } => {
+        Commands::Balance {
+            host,
+            output,
+            auth,
+            output_auth,
+            dry_run,
+            pin,
+            options,
+            throttle,
+        } => {
             log::info!("Balancing shards on {host}");
             match output {
                 Some(output) => log::info!("Sending docs to {output}"),
                 None => log::info!("Sending docs to stdout"),
             }
-            let _output = output.as_ref().unwrap_or(host);
-            todo!("balance shards on a cluster.");
+            let reciever = Receiver::parse(host, auth).expect("Failed to parse host");
+            let enforcer = Enforcer::parse(host, auth)
+                .expect("Failed to parse host")
+                .pin(*pin)
+                .throttle(throttle.clone());
+            let exporter =
+                Exporter::parse(output.as_ref(), output_auth).expect("Failed to parse output");
+            let evaluation = processor::evaluate_shard_balance(&reciever, options, None)
+                .await
+                .expect("Failed to evaluate shard balance");
+            // The plan goes to stderr so stdout can still carry the shard documents
+            let plan = Plan::from(&evaluation);
+            let docs = evaluation.shards;
+            eprint!("{plan}");
+            let summary = plan.summary(options.strategy);
+            match dry_run {
+                true => log::info!("Dry run, no shards moved on {enforcer}"),
+                false => {
+                    let moved = enforcer
+                        .enforce(&docs, &plan.moves)
+                        .await
+                        .expect("Error rerouting shards");
+                    log::info!("Moved {moved} of {} shards on {enforcer}", plan.moves.len());
+                }
+            }
+
+            let doc_count = exporter.write(docs).await.expect("Error writing docs");
+            log::info!("Wrote {doc_count} docs to {exporter}");
+            exporter
+                .write(evaluation.nodes)
+                .await
+                .expect("Error writing node docs");
+            exporter
+                .write(vec![summary])
+                .await
+                .expect("Error writing summary");
+        }
+        Commands::Capture { host, dir, auth } => {
+            log::info!("Capturing {host} to {}", dir.display());
+            let reciever = Receiver::parse(host, auth).expect("Failed to parse host");
+            let files = reciever
+                .capture(dir)
+                .await
+                .expect("Failed to capture cluster");
+            log::info!("Wrote {files} files to {}", dir.display());
         }
         Commands::Eval {
             input,
             input_auth,
             output,
             output_auth,
+            options,
         } => {
             let reciever = Receiver::parse(input, input_auth).expect("Failed to parse input");
             let exporter =
                 Exporter::parse(output.as_ref(), output_auth).expect("Failed to parse output");
-            let docs = processor::evaluate_shard_balance(&reciever)
+            let evaluation = processor::evaluate_shard_balance(&reciever, options, None)
                 .await
                 .expect("Failed to evaluate shard balance");
+            let plan = Plan::from(&evaluation);
+            let docs = evaluation.shards;
+            eprint!("{plan}");
+            let summary = plan.summary(options.strategy);
 
             match exporter.is_connected().await {
                 true => log::info!("Connected to {exporter}"),
@@ -130,21 +364,135 @@ async fn main() {
             };
             let doc_count = exporter.write(docs).await.expect("Error writing docs");
             log::info!("Wrote {doc_count} docs to {exporter}");
+            exporter
+                .write(evaluation.nodes)
+                .await
+                .expect("Error writing node docs");
+            exporter
+                .write(vec![summary])
+                .await
+                .expect("Error writing summary");
+        }
+        Commands::Recommend { input, apply, auth } => {
+            let reciever = Receiver::parse(input, auth).expect("Failed to parse input");
+            let recommendations = processor::recommend_shard_counts(&reciever)
+                .await
+                .expect("Failed to recommend shard counts");
+            print!("{recommendations}");
+
+            if *apply {
+                let exporter = Exporter::to_receiver(input, auth).expect("Failed to parse host");
+                for recommendation in recommendations.changes() {
+                    let (template, shards) = (&recommendation.template, recommendation.recommended);
+                    match client::setup::template_shards(&exporter, template, shards).await {
+                        Ok(true) => log::info!("Set {template} to {shards} primary shards"),
+                        Ok(false) => {}
+                        Err(e) => log::error!("Failed to update {template}: {e}"),
+                    }
+                }
+            }
+        }
+        Commands::Replay {
+            input,
+            output,
+            output_auth,
+            options,
+        } => {
+            let reciever = Receiver::replay(input).expect("Failed to parse input");
+            let exporter =
+                Exporter::parse(output.as_ref(), output_auth).expect("Failed to parse output");
+            Watcher::new(reciever, exporter, Duration::ZERO, options.clone())
+                .replay()
+                .await
+                .expect("Error replaying snapshots");
+        }
+        Commands::Simulate {
+            input,
+            input_auth,
+            compare,
+            options,
+        } => {
+            let reciever = Receiver::parse(input, input_auth).expect("Failed to parse input");
+            match compare {
+                true => {
+                    let comparison = processor::compare(&reciever, options)
+                        .await
+                        .expect("Failed to simulate shard balance");
+                    print!("{comparison}");
+                }
+                false => {
+                    let simulation = processor::simulate(&reciever, options)
+                        .await
+                        .expect("Failed to simulate shard balance");
+                    eprint!("{}", simulation.plan);
+                    print!("{simulation}");
+                }
+            }
         }
-        Commands::Setup { host, auth } => {
+        Commands::Setup {
+            host,
+            auth,
+            lifecycle,
+            retention,
+            kibana,
+            overwrite,
+        } => {
             log::info!("Setting up eshipster datastreams on {host}");
             let exporter = Exporter::parse(Some(host), auth).expect("Error parsing output");
-            client::setup::elasticsearch(&exporter)
+            client::setup::elasticsearch(&exporter, *lifecycle, retention)
                 .await
                 .expect("Error on Elasticsearch setup");
+            if let Some(kibana) = kibana {
+                log::info!("Importing eshipster saved objects into {kibana}");
+                let client = KibanaClient::parse(kibana, auth).expect("Error parsing Kibana");
+                client::setup::kibana(&client, *overwrite)
+                    .await
+                    .expect("Error on Kibana setup");
+            }
         }
-        Commands::Watch { host, output } => {
+        Commands::Watch {
+            host,
+            output,
+            interval,
+            enforce,
+            pin,
+            auth,
+            output_auth,
+            options,
+            throttle,
+        } => {
             log::info!("Watching shard balance on {host}");
-            match output {
-                Some(output) => log::info!("Sending docs to {output}"),
-                None => log::info!("Sending docs back to {host}"),
+            let exporter = match output {
+                Some(output) => {
+                    log::info!("Sending docs to {output}");
+                    Exporter::parse(Some(output), output_auth)
+                }
+                None => {
+                    log::info!("Sending docs back to {host}");
+                    Exporter::to_receiver(host, auth)
+                }
             }
-            todo!("the watch service.");
+            .expect("Failed to parse output");
+            let reciever = Receiver::parse(host, auth).expect("Failed to parse host");
+            let enforcer = match enforce {
+                true => Some(
+                    Enforcer::parse(host, auth)
+                        .expect("Failed to parse host")
+                        .pin(*pin)
+                        .throttle(throttle.clone()),
+                ),
+                false => None,
+            };
+            Watcher::new(
+                reciever,
+                exporter,
+                Duration::from_secs(*interval),
+                options.clone(),
+            )
+            .enforcer(enforcer)
+            .run()
+            .await
+            .expect("Error watching shard balance");
         }
     }
 }
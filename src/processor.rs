@@ -1,14 +1,153 @@
+mod balancer;
+mod capacity;
+mod constraints;
+mod disk;
+#[cfg(test)]
+mod fixtures;
 mod index_stats;
 mod lookup;
+mod node_docs;
+mod plan;
+mod rates;
+mod recommend;
+mod simulate;
+mod strategy;
+mod tiers;
 
-use crate::data::{DataStreams, IndicesSettings, IndicesStats, Node, Nodes, ShardDoc};
+use crate::data::{
+    ClusterSettings, DataStreams, IndicesSettings, IndicesStats, NodeDoc, Nodes, NodesStats,
+    ShardDoc,
+};
 use crate::receiver::Receiver;
+use clap::{Args, ValueEnum};
 use color_eyre::eyre::Result;
+use disk::Disk;
 use lookup::{Lookup, Lookups};
+use std::collections::HashMap;
+use tiers::Tier;
 
-pub async fn evaluate_shard_balance(reciever: &Receiver) -> Result<Vec<ShardDoc>> {
-    log::info!("Evaluating shard balance of {reciever}");
+pub use plan::{format_bytes, Plan};
+pub use rates::RateTracker;
+pub use recommend::Recommendations;
+pub use simulate::{Comparison, Simulation};
+pub use strategy::StrategyType;
 
+/// Options that tune how the shard balance is calculated
+#[derive(Args, Clone)]
+pub struct BalanceOptions {
+    /// Strategy used to weigh shard load (ingest, search, disk, mixed)
+    #[arg(default_value = "mixed", help = "Balancing strategy", long, value_enum)]
+    pub strategy: StrategyType,
+    /// Allowed deviation of each node's load from the tier mean, as a fraction of the mean
+    #[arg(
+        default_value_t = 0.1,
+        help = "Allowed node load deviation from the mean (0.1 = 10%)",
+        long
+    )]
+    pub tolerance: f64,
+    /// Balance each node's load in proportion to its capacity instead of evenly
+    #[arg(help = "Balance load in proportion to node capacity", long)]
+    pub capacity: bool,
+    /// Numeric node attribute holding each node's capacity, such as `capacity` for
+    /// `node.attr.capacity`. Implies `--capacity`.
+    #[arg(help = "Node attribute holding each node's capacity", long)]
+    pub capacity_attribute: Option<String>,
+}
+
+/// The shard documents of an evaluation, with the per-node totals they add up to
+pub struct Evaluation {
+    pub shards: Vec<ShardDoc>,
+    pub nodes: Vec<NodeDoc>,
+    /// Disk of each node with filesystem stats before the plan, by node name
+    pub disks: HashMap<String, Disk>,
+    /// The data tiers with their nodes, including those without any shards
+    pub tiers: Vec<Tier>,
+}
+
+/// Everything an evaluation reads from the cluster apart from the shards, which each plan
+/// takes its own copy of
+struct ClusterState {
+    lookups: Lookups,
+    cluster_settings: ClusterSettings,
+    disks: HashMap<String, Disk>,
+    timestamp: i64,
+}
+
+/// Collect shard stats and plan the balance. With a rate tracker, shards are weighed by their
+/// activity since the previous evaluation instead of over their lifetime.
+pub async fn evaluate_shard_balance(
+    reciever: &Receiver,
+    options: &BalanceOptions,
+    rates: Option<&mut RateTracker>,
+) -> Result<Evaluation> {
+    let (state, mut shards) = fetch_cluster_state(reciever).await?;
+    if let Some(rates) = rates {
+        rates.update(&mut shards);
+    }
+    plan_shard_balance(&state, shards, options)
+}
+
+async fn fetch_cluster_state(reciever: &Receiver) -> Result<(ClusterState, Vec<ShardDoc>)> {
+    log::info!("Collecting shard stats of {reciever}");
+    let timestamp = reciever.timestamp();
+    let lookups = fetch_lookups(reciever).await?;
+
+    let cluster_settings = fetch_cluster_settings(reciever).await;
+    let disks = match reciever.get::<NodesStats>().await {
+        Ok(nodes_stats) => Disk::by_node(&nodes_stats),
+        Err(e) => {
+            log::warn!("Node filesystem stats unavailable, disk watermarks are ignored: {e}");
+            HashMap::new()
+        }
+    };
+    log::info!("Disk usage entries: {}", disks.len());
+
+    let indices_stats: IndicesStats = reciever.get().await?;
+    log::info!("Indices stats entires: {}", indices_stats.indices.len());
+
+    let shards = index_stats::extract_shard_docs(indices_stats, &lookups, timestamp)?;
+    log::debug!("Shards starting: {}", &shards.len());
+    let state = ClusterState {
+        lookups,
+        cluster_settings,
+        disks,
+        timestamp,
+    };
+    Ok((state, shards))
+}
+
+fn plan_shard_balance(
+    state: &ClusterState,
+    mut shards: Vec<ShardDoc>,
+    options: &BalanceOptions,
+) -> Result<Evaluation> {
+    log::info!("Evaluating {} shard balance", options.strategy);
+    let tiers = balancer::rebalance_shards(
+        &state.lookups,
+        &mut shards,
+        options,
+        &state.cluster_settings,
+        &state.disks,
+    )?;
+    log::debug!("Shards rebalanced: {}", &shards.len());
+    let nodes =
+        node_docs::extract_node_docs(state.lookups.node.get_entries(), &shards, state.timestamp);
+    Ok(Evaluation {
+        shards,
+        nodes,
+        disks: state.disks.clone(),
+        tiers,
+    })
+}
+
+/// Recommend the primary shard count of each data stream template's next generation
+pub async fn recommend_shard_counts(reciever: &Receiver) -> Result<Recommendations> {
+    log::info!("Recommending data stream shard counts for {reciever}");
+    let lookups = fetch_lookups(reciever).await?;
+    Ok(recommend::recommend_shard_counts(&lookups))
+}
+
+async fn fetch_lookups(reciever: &Receiver) -> Result<Lookups> {
     let lookups = Lookups {
         data_stream: Lookup::from(reciever.get::<DataStreams>().await?),
         index: Lookup::from(reciever.get::<IndicesSettings>().await?),
@@ -21,43 +160,57 @@ pub async fn evaluate_shard_balance(reciever: &Receiver) -> Result<Vec<ShardDoc>
 
     // env_logger outputs to stderr, so we can cleanly redirect stdout to a file for debugging
     if log::max_level() >= log::Level::Trace {
-        println!("{}", lookups.data_stream.to_string());
-        println!("{}", lookups.index.to_string());
-        println!("{}", lookups.node.to_string());
+        println!("{}", lookups.data_stream);
+        println!("{}", lookups.index);
+        println!("{}", lookups.node);
     }
+    Ok(lookups)
+}
 
-    let indices_stats: IndicesStats = reciever.get().await?;
-    log::info!("Indices stats entires: {}", indices_stats.indices.len());
-
-    let mut shards = index_stats::extract_shard_docs(indices_stats, &lookups)?;
-    log::debug!("Shards starting: {}", &shards.len());
-    rebalance_shards(lookups, &mut shards)?;
-    log::debug!("Shards rebalanced: {}", &shards.len());
-    Ok(shards)
+/// Evaluate the shard balance and apply the plan to the in-memory model, reporting the
+/// resulting node loads, disk usage and balance scores without touching the cluster
+pub async fn simulate(reciever: &Receiver, options: &BalanceOptions) -> Result<Simulation> {
+    let (state, shards) = fetch_cluster_state(reciever).await?;
+    simulate_plan(&state, shards, options)
 }
 
-fn rebalance_shards(lookups: Lookups, shards: &mut Vec<ShardDoc>) -> Result<()> {
-    log::info!("Rebalancing shards");
-    let role = String::from("data_hot");
-    let hot_nodes: Vec<&Node> = lookups
-        .node
-        .get_entries()
+/// Simulate every strategy against the same cluster state, which is only read once
+pub async fn compare(reciever: &Receiver, options: &BalanceOptions) -> Result<Comparison> {
+    let (state, shards) = fetch_cluster_state(reciever).await?;
+    let simulations = StrategyType::value_variants()
         .iter()
-        .filter(|node| node.roles.contains(&role))
-        .collect();
-
-    shards.sort_unstable_by(|a, b| {
-        a.data_stream_name()
-            .cmp(&b.data_stream_name())
-            .then(a.index_name().cmp(&b.index_name()))
-            .then(a.shard_number().cmp(&b.shard_number()))
-            .then(a.primary().cmp(&b.primary()))
-    });
-
-    shards.iter_mut().enumerate().for_each(|(i, shard)| {
-        let node_name = hot_nodes[i % hot_nodes.len()].name.clone();
-        shard.set_desired_node(node_name)
-    });
-
-    Ok(())
+        .map(|strategy| {
+            let options = BalanceOptions {
+                strategy: *strategy,
+                ..options.clone()
+            };
+            simulate_plan(&state, shards.clone(), &options)
+        })
+        .collect::<Result<_>>()?;
+    Ok(Comparison(simulations))
+}
+
+fn simulate_plan(
+    state: &ClusterState,
+    shards: Vec<ShardDoc>,
+    options: &BalanceOptions,
+) -> Result<Simulation> {
+    let evaluation = plan_shard_balance(state, shards, options)?;
+    let plan = Plan::from(&evaluation);
+    Ok(Simulation::new(
+        options.strategy,
+        plan,
+        &evaluation.disks,
+        &state.cluster_settings,
+    ))
+}
+
+async fn fetch_cluster_settings(reciever: &Receiver) -> ClusterSettings {
+    match reciever.get::<ClusterSettings>().await {
+        Ok(settings) => settings,
+        Err(e) => {
+            log::warn!("Cluster settings unavailable, allocation awareness is ignored: {e}");
+            ClusterSettings::default()
+        }
+    }
 }
@@ -0,0 +1,693 @@
+use super::capacity;
+use super::constraints::{shard_id, Allocation, ShardId};
+use super::disk::Disk;
+use super::lookup::Lookups;
+use super::plan::format_bytes;
+use super::strategy::{ShardLoad, Strategy};
+use super::tiers::{self, Tier};
+use super::BalanceOptions;
+use crate::data::{ClusterSettings, Node, ShardDoc};
+use color_eyre::eyre::{eyre, Result};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Assigned shards and accumulated weight of a node while building the plan
+struct NodeBin<'a> {
+    node: &'a Node,
+    /// Capacity relative to the other nodes of the tier, which average 1
+    capacity: f64,
+    shards: usize,
+    weight: f64,
+    /// Weight of the data stream write index shards alone
+    write_weight: f64,
+}
+
+/// Write index shards carry the ingest load, so they are spread first and everything else is
+/// balanced around them.
+#[derive(Clone, Copy, PartialEq)]
+enum Pass {
+    WriteIndices,
+    Remaining,
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pass::WriteIndices => write!(f, "write indices"),
+            Pass::Remaining => write!(f, "remaining shards"),
+        }
+    }
+}
+
+/// Swaps relocate two shards, so they must shift at least this share of the mean node weight
+const MIN_SWAP_SHARE: f64 = 0.01;
+
+/// A shard a pass may relocate, with the bin it is on and what moving it weighs and costs
+struct Candidate {
+    shard: usize,
+    bin: usize,
+    weight: f64,
+    /// Relocated bytes plus the fixed overhead of a move
+    cost: f64,
+    id: ShardId,
+}
+
+/// A change to the placement, as candidate and bin indices
+enum Change {
+    Move(usize, usize),
+    /// Exchange the bins of two candidates
+    Swap(usize, usize),
+}
+
+/// Moves cost their relocated bytes plus a fixed overhead, so fewer moves are preferred
+const MOVE_OVERHEAD_BYTES: f64 = 16.0 * 1024.0 * 1024.0;
+
+/// Balance each data tier independently, since shards can only move between nodes of the tier
+/// their index prefers. Returns the tiers with their node capacities and shards.
+pub fn rebalance_shards(
+    lookups: &Lookups,
+    shards: &mut [ShardDoc],
+    options: &BalanceOptions,
+    cluster_settings: &ClusterSettings,
+    disks: &HashMap<String, Disk>,
+) -> Result<Vec<Tier>> {
+    log::info!("Rebalancing shards");
+    let nodes = lookups.node.get_entries();
+    let strategy = options.strategy.strategy();
+    let mut tier_shards: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, shard) in shards.iter_mut().enumerate() {
+        match tiers::shard_tier(shard, nodes) {
+            Some(tier) => tier_shards.entry(tier).or_default().push(i),
+            None => {
+                log::debug!("No tier for {}, keeping placement", shard.index_name());
+                let current = shard.node_name().unwrap_or_default().to_string();
+                shard.set_desired_node(current);
+            }
+        }
+    }
+
+    // Nodes can hold several tiers, so each tier's plan is added to the projected disks before
+    // balancing the next, and the shards of every other tier count towards each node's limit
+    let capacity_attribute = options.capacity_attribute.as_deref();
+    let by_capacity = options.capacity || capacity_attribute.is_some();
+    let mut projected = disks.clone();
+    let (mut moves, mut bytes) = (0, 0);
+    let mut tiers = Vec::new();
+    for tier in tiers::TIERS {
+        let members = tier_shards.remove(tier).unwrap_or_default();
+        let tier_nodes = tiers::tier_nodes(nodes, tier);
+        if tier_nodes.is_empty() {
+            match members.is_empty() {
+                true => continue,
+                false => return Err(eyre!("No {tier} nodes to balance shards across")),
+            }
+        }
+        let capacities = match by_capacity {
+            true => {
+                capacity::node_capacities(&tier_nodes, disks, strategy.as_ref(), capacity_attribute)
+            }
+            false => vec![1.0; tier_nodes.len()],
+        };
+        let names = tier_nodes.iter().map(|node| node.name.clone());
+        let tier_capacities: Vec<(String, f64)> = names.zip(capacities.clone()).collect();
+        if members.is_empty() {
+            tiers.push(Tier {
+                nodes: tier_capacities,
+                shards: members,
+            });
+            continue;
+        }
+        log::info!(
+            "Balancing {} shards across {} {tier} nodes",
+            members.len(),
+            tier_nodes.len()
+        );
+        let allocation = Allocation::new(
+            tier_nodes.clone(),
+            shards,
+            cluster_settings,
+            &without_shards(&projected, shards, &members),
+        )
+        .placed_elsewhere(&shards_elsewhere(shards, &members));
+        let plan = TierBalancer::new(
+            tier_nodes,
+            capacities,
+            shards,
+            &members,
+            strategy.as_ref(),
+            allocation,
+        )
+        .balance(options.tolerance);
+        moves += plan.0;
+        bytes += plan.1;
+        for i in &members {
+            let shard = &shards[*i];
+            let size = shard.stats().size_in_bytes();
+            if let Some(disk) = shard.node_name().and_then(|node| projected.get_mut(node)) {
+                *disk = disk.after(size, 0);
+            }
+            if let Some(disk) = shard
+                .desired_node()
+                .and_then(|node| projected.get_mut(node))
+            {
+                *disk = disk.after(0, size);
+            }
+        }
+        tiers.push(Tier {
+            nodes: tier_capacities,
+            shards: members,
+        });
+    }
+    log::info!(
+        "Planned {moves} shard moves relocating {}",
+        format_bytes(bytes)
+    );
+    Ok(tiers)
+}
+
+/// The disks of the nodes without the given shards, which the tier balancer places again
+fn without_shards(
+    disks: &HashMap<String, Disk>,
+    shards: &[ShardDoc],
+    members: &[usize],
+) -> HashMap<String, Disk> {
+    let mut disks = disks.clone();
+    for i in members {
+        let shard = &shards[*i];
+        if let Some(disk) = shard.node_name().and_then(|node| disks.get_mut(node)) {
+            *disk = disk.after(shard.stats().size_in_bytes(), 0);
+        }
+    }
+    disks
+}
+
+/// Shards on each node other than the given ones, where they are planned to be if their tier
+/// has been balanced already and where they are now otherwise
+fn shards_elsewhere(shards: &[ShardDoc], members: &[usize]) -> HashMap<String, usize> {
+    let members: HashSet<usize> = members.iter().copied().collect();
+    let mut node_shards = HashMap::new();
+    for (i, shard) in shards.iter().enumerate() {
+        if members.contains(&i) {
+            continue;
+        }
+        if let Some(node) = shard.desired_node().or(shard.node_name()) {
+            *node_shards.entry(node.to_string()).or_insert(0) += 1;
+        }
+    }
+    node_shards
+}
+
+/// Balances one tier, starting from the current placement and making the cheapest moves that
+/// bring every node's weight within the tolerance of its share of the tier, in proportion to its
+/// capacity.
+struct TierBalancer<'a, 's> {
+    allocation: Allocation<'a>,
+    bins: Vec<NodeBin<'a>>,
+    members: &'s [usize],
+    /// The bin each member shard is placed on
+    placement: HashMap<usize, usize>,
+    shards: &'s mut [ShardDoc],
+    weights: HashMap<usize, f64>,
+    /// Weights of the write index shards relative to each other
+    write_weights: HashMap<usize, f64>,
+}
+
+impl<'a, 's> TierBalancer<'a, 's> {
+    fn new(
+        nodes: Vec<&'a Node>,
+        capacities: Vec<f64>,
+        shards: &'s mut [ShardDoc],
+        members: &'s [usize],
+        strategy: &dyn Strategy,
+        allocation: Allocation<'a>,
+    ) -> Self {
+        let bins = nodes
+            .iter()
+            .zip(capacities)
+            .map(|(node, capacity)| NodeBin {
+                node,
+                capacity,
+                shards: 0,
+                weight: 0.0,
+                write_weight: 0.0,
+            })
+            .collect();
+
+        // Weights are relative to the tier, not the whole cluster
+        let writes: Vec<usize> = members
+            .iter()
+            .filter(|i| shards[**i].is_write_index())
+            .copied()
+            .collect();
+        let weights = weigh(shards, members, strategy);
+        let write_weights = weigh(shards, &writes, strategy);
+
+        Self {
+            allocation,
+            bins,
+            members,
+            placement: HashMap::new(),
+            shards,
+            weights,
+            write_weights,
+        }
+    }
+
+    /// Returns the number of moves planned and the bytes they relocate
+    fn balance(mut self, tolerance: f64) -> (usize, u64) {
+        self.place_current();
+        self.place_outsiders();
+        self.improve(tolerance, Pass::WriteIndices);
+        self.improve(tolerance, Pass::Remaining);
+
+        let (mut moves, mut bytes) = (0, 0);
+        for i in self.members {
+            let shard = &mut self.shards[*i];
+            let current = shard.node_name().unwrap_or_default().to_string();
+            let desired = match self.placement.get(i) {
+                Some(bin) => self.bins[*bin].node.name.clone(),
+                None => current.clone(),
+            };
+            if desired != current {
+                moves += 1;
+                bytes += shard.stats().size_in_bytes();
+            }
+            shard.set_desired_node(desired);
+        }
+
+        self.bins.iter().for_each(|bin| {
+            log::debug!(
+                "Node {} capacity: {:.2}, desired shards: {}, weight: {:.4}, write weight: {:.4}",
+                bin.node.name,
+                bin.capacity,
+                bin.shards,
+                bin.weight,
+                bin.write_weight
+            )
+        });
+        log::info!(
+            "Tier plan has {moves} moves relocating {}",
+            format_bytes(bytes)
+        );
+        (moves, bytes)
+    }
+
+    /// Start from where each shard already lives within the tier
+    fn place_current(&mut self) {
+        for i in self.members {
+            let current = self.shards[*i].node_name();
+            if let Some(bin) = self
+                .bins
+                .iter()
+                .position(|bin| Some(bin.node.name.as_str()) == current)
+            {
+                self.place(*i, bin);
+            }
+        }
+    }
+
+    /// Shards on nodes outside the tier must move, write indices then heaviest first onto the
+    /// lightest valid node
+    fn place_outsiders(&mut self) {
+        let mut outsiders: Vec<usize> = self
+            .members
+            .iter()
+            .filter(|i| !self.placement.contains_key(i))
+            .copied()
+            .collect();
+        let (shards, weights) = (&self.shards, &self.weights);
+        outsiders.sort_by(|a, b| {
+            shards[*b]
+                .is_write_index()
+                .cmp(&shards[*a].is_write_index())
+                .then(
+                    weights[b]
+                        .partial_cmp(&weights[a])
+                        .unwrap_or(Ordering::Equal),
+                )
+                .then(
+                    shards[*a]
+                        .data_stream_name()
+                        .cmp(&shards[*b].data_stream_name()),
+                )
+                .then(shards[*a].index_name().cmp(&shards[*b].index_name()))
+                .then(shards[*a].shard_number().cmp(&shards[*b].shard_number()))
+        });
+
+        for i in outsiders {
+            let pass = self.pass_of(i);
+            let target = self
+                .bins
+                .iter()
+                .enumerate()
+                .filter(|(n, _)| self.allocation.can_allocate(*n, &self.shards[i]))
+                .min_by(|(_, a), (_, b)| {
+                    a.load(pass)
+                        .partial_cmp(&b.load(pass))
+                        .unwrap_or(Ordering::Equal)
+                        .then(a.weight.partial_cmp(&b.weight).unwrap_or(Ordering::Equal))
+                        .then(a.shards.cmp(&b.shards))
+                })
+                .map(|(n, _)| n);
+            match target {
+                Some(bin) => self.place(i, bin),
+                None => log::warn!(
+                    "No valid node for {}[{}], keeping current placement",
+                    self.shards[i].index_name(),
+                    self.shards[i].shard_number()
+                ),
+            }
+        }
+    }
+
+    /// Repeatedly apply the move with the best variance reduction per relocated byte, until every
+    /// node is within the tolerance of the mean or nothing improves. When no single move helps,
+    /// for example because the shards that would fit are blocked by allocation constraints, two
+    /// shards of different weights are swapped between nodes instead.
+    /// Loads are per unit of capacity, so a node twice the size of another is balanced at twice
+    /// its weight.
+    /// Each pass only moves its own shards, so balancing older generations never undoes the
+    /// spread of the write indices.
+    fn improve(&mut self, tolerance: f64, pass: Pass) {
+        let mut candidates = self.candidates(pass);
+        let limit = 2 * self.members.len();
+        for _ in 0..limit {
+            if self.within_tolerance(tolerance, pass) {
+                return;
+            }
+            match self
+                .best_move(&candidates, pass)
+                .or_else(|| self.best_swap(&candidates, pass))
+            {
+                Some(Change::Move(c, to)) => {
+                    let candidate = &mut candidates[c];
+                    self.unplace(candidate.shard, candidate.bin);
+                    self.place(candidate.shard, to);
+                    candidate.bin = to;
+                }
+                Some(Change::Swap(a, b)) => {
+                    let (from, to) = (candidates[a].bin, candidates[b].bin);
+                    self.unplace(candidates[a].shard, from);
+                    self.unplace(candidates[b].shard, to);
+                    self.place(candidates[a].shard, to);
+                    self.place(candidates[b].shard, from);
+                    candidates[a].bin = to;
+                    candidates[b].bin = from;
+                }
+                None => break,
+            }
+        }
+        if !self.within_tolerance(tolerance, pass) {
+            let mean = self.mean(pass);
+            let heaviest = &self.bins[self.extreme_bin(Ordering::Greater, pass)];
+            let lightest = &self.bins[self.extreme_bin(Ordering::Less, pass)];
+            log::warn!(
+                "Could not balance {pass} within {:.0}% of the mean, {} is at {:.0}% and {} at {:.0}%",
+                tolerance * 100.0,
+                heaviest.node.name,
+                heaviest.load(pass) / mean * 100.0,
+                lightest.node.name,
+                lightest.load(pass) / mean * 100.0
+            );
+        }
+    }
+
+    /// The weight per unit of capacity every node would carry if the tier were perfectly balanced
+    fn mean(&self, pass: Pass) -> f64 {
+        self.bins.iter().map(|bin| bin.total(pass)).sum::<f64>()
+            / self.bins.iter().map(|bin| bin.capacity).sum::<f64>()
+    }
+
+    fn within_tolerance(&self, tolerance: f64, pass: Pass) -> bool {
+        let mean = self.mean(pass);
+        let band = tolerance * mean;
+        mean <= 0.0
+            || (self.bins[self.extreme_bin(Ordering::Greater, pass)].load(pass) - mean <= band
+                && mean - self.bins[self.extreme_bin(Ordering::Less, pass)].load(pass) <= band)
+    }
+
+    /// Change in the capacity weighted sum of squared deviations from the mean when the weight
+    /// moves from one bin to another
+    fn gain(&self, weight: f64, from: usize, to: usize, pass: Pass) -> f64 {
+        let (from, to) = (&self.bins[from], &self.bins[to]);
+        2.0 * weight * (from.load(pass) - to.load(pass))
+            - weight * weight * (1.0 / from.capacity + 1.0 / to.capacity)
+    }
+
+    /// The started shards of a pass with the bin they are placed on, by weight per cost from
+    /// highest. Their weights and costs don't change while the pass runs, so they are worked out
+    /// once.
+    fn candidates(&self, pass: Pass) -> Vec<Candidate> {
+        let mut candidates: Vec<Candidate> = self
+            .members
+            .iter()
+            .filter(|i| self.pass_of(**i) == pass && self.shards[**i].state() == "STARTED")
+            .filter_map(|i| {
+                let shard = &self.shards[*i];
+                Some(Candidate {
+                    shard: *i,
+                    bin: *self.placement.get(i)?,
+                    weight: self.weight(*i, pass),
+                    cost: shard.stats().size_in_bytes() as f64 + MOVE_OVERHEAD_BYTES,
+                    id: shard_id(shard),
+                })
+            })
+            .collect();
+        candidates.sort_by(|a, b| {
+            (b.weight / b.cost)
+                .partial_cmp(&(a.weight / a.cost))
+                .unwrap_or(Ordering::Equal)
+        });
+        candidates
+    }
+
+    /// The single shard move between any two nodes with the best gain per relocated byte. A move
+    /// gains at most twice its weight times how far the node it leaves is above the lightest, so
+    /// with candidates by weight per cost, the search stops once none left could do better.
+    fn best_move(&mut self, candidates: &[Candidate], pass: Pass) -> Option<Change> {
+        let heaviest = self.bins[self.extreme_bin(Ordering::Greater, pass)].load(pass);
+        let lightest = self.bins[self.extreme_bin(Ordering::Less, pass)].load(pass);
+        let mut best: Option<(f64, Change)> = None;
+        for (c, candidate) in candidates.iter().enumerate() {
+            let from = candidate.bin;
+            let ratio = 2.0 * candidate.weight / candidate.cost;
+            let best_score = best.as_ref().map_or(0.0, |(best, _)| *best);
+            if ratio * (heaviest - lightest) <= best_score {
+                break;
+            }
+            if ratio * (self.bins[from].load(pass) - lightest) <= best_score {
+                continue;
+            }
+            for to in 0..self.bins.len() {
+                let gain = self.gain(candidate.weight, from, to, pass);
+                if to == from || gain <= f64::EPSILON {
+                    continue;
+                }
+                let score = gain / candidate.cost;
+                if best.as_ref().is_some_and(|(best, _)| *best >= score) {
+                    continue;
+                }
+                if self
+                    .allocation
+                    .can_move(from, to, &self.shards[candidate.shard])
+                {
+                    best = Some((score, Change::Move(c, to)));
+                }
+            }
+        }
+        best.map(|(_, change)| change)
+    }
+
+    /// The exchange of a heavier shard on the heaviest node for a lighter one on the lightest
+    /// node with the best gain per relocated byte. Only those two nodes' shards are paired, so
+    /// the search grows with the product of their shard counts rather than the square of the
+    /// tier's.
+    fn best_swap(&mut self, candidates: &[Candidate], pass: Pass) -> Option<Change> {
+        let (from, to) = (
+            self.extreme_bin(Ordering::Greater, pass),
+            self.extreme_bin(Ordering::Less, pass),
+        );
+        if from == to {
+            return None;
+        }
+        let on_bin = |bin: usize| {
+            candidates
+                .iter()
+                .enumerate()
+                .filter(move |(_, candidate)| candidate.bin == bin)
+        };
+        let lightest: Vec<(usize, &Candidate)> = on_bin(to).collect();
+        let least = MIN_SWAP_SHARE * self.mean(pass);
+        let mut best: Option<(f64, Change)> = None;
+        for (a, heavier) in on_bin(from) {
+            for (b, lighter) in &lightest {
+                let weight = heavier.weight - lighter.weight;
+                if weight < least || heavier.id == lighter.id {
+                    continue;
+                }
+                let gain = self.gain(weight, from, to, pass);
+                if gain <= f64::EPSILON {
+                    continue;
+                }
+                let score = gain / (heavier.cost + lighter.cost);
+                if best.as_ref().is_some_and(|(best, _)| *best >= score) {
+                    continue;
+                }
+                let (a_shard, b_shard) = (&self.shards[heavier.shard], &self.shards[lighter.shard]);
+                if self.allocation.can_swap((from, a_shard), (to, b_shard)) {
+                    best = Some((score, Change::Swap(a, *b)));
+                }
+            }
+        }
+        best.map(|(_, change)| change)
+    }
+
+    fn extreme_bin(&self, ordering: Ordering, pass: Pass) -> usize {
+        let mut extreme = 0;
+        for (n, bin) in self.bins.iter().enumerate() {
+            if bin.load(pass).partial_cmp(&self.bins[extreme].load(pass)) == Some(ordering) {
+                extreme = n;
+            }
+        }
+        extreme
+    }
+
+    fn pass_of(&self, i: usize) -> Pass {
+        match self.write_weights.contains_key(&i) {
+            true => Pass::WriteIndices,
+            false => Pass::Remaining,
+        }
+    }
+
+    /// The weight a shard contributes to the load a pass balances
+    fn weight(&self, i: usize, pass: Pass) -> f64 {
+        match pass {
+            Pass::WriteIndices => self.write_weights[&i],
+            Pass::Remaining => self.weights[&i],
+        }
+    }
+
+    fn place(&mut self, i: usize, bin: usize) {
+        self.allocation.allocate(bin, &self.shards[i]);
+        self.bins[bin].weight += self.weights[&i];
+        self.bins[bin].write_weight += self.write_weights.get(&i).unwrap_or(&0.0);
+        self.bins[bin].shards += 1;
+        self.placement.insert(i, bin);
+    }
+
+    fn unplace(&mut self, i: usize, bin: usize) {
+        self.allocation.deallocate(bin, &self.shards[i]);
+        self.bins[bin].weight -= self.weights[&i];
+        self.bins[bin].write_weight -= self.write_weights.get(&i).unwrap_or(&0.0);
+        self.bins[bin].shards -= 1;
+        self.placement.remove(&i);
+    }
+}
+
+impl NodeBin<'_> {
+    /// The weight balanced by a pass
+    fn total(&self, pass: Pass) -> f64 {
+        match pass {
+            Pass::WriteIndices => self.write_weight,
+            Pass::Remaining => self.weight,
+        }
+    }
+
+    /// The weight balanced by a pass per unit of capacity
+    fn load(&self, pass: Pass) -> f64 {
+        self.total(pass) / self.capacity
+    }
+}
+
+/// Weigh each shard by its share of the combined load of the given shards
+fn weigh(shards: &[ShardDoc], members: &[usize], strategy: &dyn Strategy) -> HashMap<usize, f64> {
+    // Once rates are known, shards without them fall back one by one so a few new or moved
+    // shards don't switch the whole tier back to lifetime totals
+    let recent = members.iter().any(|i| shards[*i].rates().is_some());
+    let loads: Vec<(usize, ShardLoad)> = members
+        .iter()
+        .map(|i| match recent {
+            true => (*i, ShardLoad::recent(&shards[*i])),
+            false => (*i, ShardLoad::from(&shards[*i])),
+        })
+        .collect();
+    let mut totals = ShardLoad::default();
+    loads.iter().for_each(|(_, load)| totals.add(load));
+    loads
+        .iter()
+        .map(|(i, load)| (*i, strategy.weigh(&load.share_of(&totals))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::fixtures::{cluster_settings, index, node, shard};
+    use crate::processor::strategy;
+    use serde_json::json;
+
+    const MIB: u64 = 1024 * 1024;
+
+    /// Balance shards of the given sizes, each its own index without replicas, and return the
+    /// bytes on every node afterwards
+    fn balance(placement: &[&[u64]], settings: ClusterSettings) -> Vec<u64> {
+        let nodes: Vec<Node> = (0..placement.len())
+            .map(|n| node(&format!("node-{n}"), &["data_hot"], json!({})))
+            .collect();
+        let mut shards = Vec::new();
+        for (n, sizes) in placement.iter().enumerate() {
+            for size in *sizes {
+                let index = index(
+                    &format!("index-{}", shards.len()),
+                    json!({ "number_of_replicas": "0" }),
+                );
+                shards.push(shard(&index, 0, true, &nodes[n], size * MIB));
+            }
+        }
+        let members: Vec<usize> = (0..shards.len()).collect();
+        let allocation =
+            Allocation::new(nodes.iter().collect(), &shards, &settings, &HashMap::new());
+        TierBalancer::new(
+            nodes.iter().collect(),
+            vec![1.0; nodes.len()],
+            &mut shards,
+            &members,
+            &strategy::Disk,
+            allocation,
+        )
+        .balance(0.1);
+
+        nodes
+            .iter()
+            .map(|node| {
+                shards
+                    .iter()
+                    .filter(|shard| shard.desired_node() == Some(node.name.as_str()))
+                    .map(|shard| shard.stats().size_in_bytes() / MIB)
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn swaps_when_no_single_move_helps() {
+        // Moving any one shard overshoots, trading 30 for 15 leaves 55 and 65
+        let bytes = balance(&[&[40, 30], &[35, 15]], cluster_settings(json!({})));
+        assert!(
+            bytes.iter().all(|bytes| bytes.abs_diff(60) <= 6),
+            "{bytes:?}"
+        );
+    }
+
+    #[test]
+    fn moves_between_nodes_other_than_the_extremes() {
+        // The heaviest node's shard is too big to move and the lightest is full, but the second
+        // node can still hand a shard to the third
+        let settings = cluster_settings(json!({
+            "cluster.routing.allocation.total_shards_per_node": "2"
+        }));
+        let bytes = balance(&[&[70], &[30, 20], &[5], &[1, 1]], settings);
+        assert_eq!(bytes[..2], [70, 30], "{bytes:?}");
+    }
+}
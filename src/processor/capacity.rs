@@ -0,0 +1,155 @@
+use super::disk::Disk;
+use super::strategy::{ShardLoad, Strategy};
+use crate::data::Node;
+use std::collections::HashMap;
+
+/// The capacity of each node relative to the others, where the average node is 1. Taken from a
+/// numeric node attribute when given, otherwise derived from the resources each strategy
+/// balances: processors for write load, JVM heap for search and filesystem size for disk.
+pub fn node_capacities(
+    nodes: &[&Node],
+    disks: &HashMap<String, Disk>,
+    strategy: &dyn Strategy,
+    attribute: Option<&str>,
+) -> Vec<f64> {
+    if let Some(attribute) = attribute {
+        let capacities: Option<Vec<f64>> = nodes
+            .iter()
+            .map(|node| {
+                node.attribute(attribute)
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .filter(|capacity| *capacity > 0.0)
+            })
+            .collect();
+        match capacities {
+            Some(capacities) => return normalize(capacities),
+            None => log::warn!(
+                "Not every node has a positive numeric {attribute} attribute, deriving capacity"
+            ),
+        }
+    }
+
+    // A resource missing from any node is treated as equal on every node
+    let resource = |value: &dyn Fn(&Node) -> Option<u64>| -> Vec<f64> {
+        let values: Option<Vec<f64>> = nodes
+            .iter()
+            .map(|node| value(node).map(|value| value as f64))
+            .collect();
+        values.unwrap_or_else(|| vec![1.0; nodes.len()])
+    };
+    let processors = resource(&Node::allocated_processors);
+    let heap = resource(&Node::heap_max_in_bytes);
+    let disk = resource(&|node: &Node| disks.get(&node.name).map(|disk| disk.total));
+
+    let capacities: Vec<ShardLoad> = (0..nodes.len())
+        .map(|n| ShardLoad {
+            search: heap[n],
+            size_in_bytes: disk[n],
+            write_load: processors[n],
+        })
+        .collect();
+    let mut totals = ShardLoad::default();
+    capacities.iter().for_each(|capacity| totals.add(capacity));
+    normalize(
+        capacities
+            .iter()
+            .map(|capacity| strategy.weigh(&capacity.share_of(&totals)))
+            .collect(),
+    )
+}
+
+/// The smallest capacity a node is given, since loads are divided by it. A node with none of a
+/// resource, such as a filesystem reported as 0 bytes, still takes a sliver of the tier's shards.
+const MIN_CAPACITY: f64 = 0.01;
+
+/// Scale capacities so they average 1, or make them equal when they are all zero
+fn normalize(capacities: Vec<f64>) -> Vec<f64> {
+    let mean = capacities.iter().sum::<f64>() / capacities.len().max(1) as f64;
+    match mean > 0.0 {
+        true => capacities
+            .iter()
+            .map(|capacity| (capacity / mean).max(MIN_CAPACITY))
+            .collect(),
+        false => vec![1.0; capacities.len()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures;
+    use super::super::strategy::{Disk as DiskOnly, Ingest};
+    use super::*;
+    use serde_json::json;
+
+    fn disks(totals: &[(&str, u64)]) -> HashMap<String, Disk> {
+        totals
+            .iter()
+            .map(|(name, total)| {
+                (
+                    name.to_string(),
+                    Disk {
+                        total: *total,
+                        used: 0,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn assert_capacities(actual: Vec<f64>, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+        }
+    }
+
+    #[test]
+    fn attribute_capacities_are_normalized_to_average_one() {
+        let a = fixtures::node("hot-1", &["data_hot"], json!({ "size": "1" }));
+        let b = fixtures::node("hot-2", &["data_hot"], json!({ "size": "3" }));
+        let capacities = node_capacities(&[&a, &b], &HashMap::new(), &Ingest, Some("size"));
+        assert_capacities(capacities, &[0.5, 1.5]);
+    }
+
+    #[test]
+    fn invalid_attribute_falls_back_to_derived_capacity() {
+        let a = fixtures::node("hot-1", &["data_hot"], json!({ "size": "2" }));
+        let b = fixtures::node("hot-2", &["data_hot"], json!({ "size": "0" }));
+        let c = fixtures::node("hot-3", &["data_hot"], json!({}));
+        let disks = disks(&[("hot-1", 100), ("hot-2", 300), ("hot-3", 200)]);
+        // Neither a zero nor a missing attribute is used, so disk size decides instead
+        let capacities = node_capacities(&[&a, &b], &disks, &DiskOnly, Some("size"));
+        assert_capacities(capacities, &[0.5, 1.5]);
+        let capacities = node_capacities(&[&a, &c], &disks, &DiskOnly, Some("size"));
+        assert_capacities(capacities, &[2.0 / 3.0, 4.0 / 3.0]);
+    }
+
+    #[test]
+    fn resource_missing_from_a_node_counts_as_equal() {
+        let a = fixtures::node("hot-1", &["data_hot"], json!({}));
+        let b = fixtures::node("hot-2", &["data_hot"], json!({}));
+        let disks = disks(&[("hot-1", 100)]);
+        let capacities = node_capacities(&[&a, &b], &disks, &DiskOnly, None);
+        assert_capacities(capacities, &[1.0, 1.0]);
+    }
+
+    #[test]
+    fn derived_capacities_weigh_resources_by_strategy() {
+        let a = fixtures::node("hot-1", &["data_hot"], json!({}));
+        let b = fixtures::node("hot-2", &["data_hot"], json!({}));
+        // Processors and heap are equal, so only the disk share of the ingest weight differs
+        let disks = disks(&[("hot-1", 100), ("hot-2", 300)]);
+        let capacities = node_capacities(&[&a, &b], &disks, &Ingest, None);
+        assert_capacities(capacities, &[0.9, 1.1]);
+    }
+
+    #[test]
+    fn capacities_are_never_zero() {
+        let a = fixtures::node("hot-1", &["data_hot"], json!({}));
+        let b = fixtures::node("hot-2", &["data_hot"], json!({}));
+        let disks = disks(&[("hot-1", 0), ("hot-2", 100)]);
+        let capacities = node_capacities(&[&a, &b], &disks, &DiskOnly, None);
+        assert_capacities(capacities, &[MIN_CAPACITY, 2.0]);
+        assert_capacities(normalize(vec![0.0, 0.0]), &[1.0, 1.0]);
+    }
+}
@@ -0,0 +1,349 @@
+use super::disk::{Disk, DiskLevel, Watermarks};
+use crate::data::{ClusterSettings, Node, ShardDoc};
+use std::collections::{HashMap, HashSet};
+
+/// Identifies every copy of the same shard: the index name and shard number
+pub type ShardId = (String, u16);
+
+/// Tracks planned shard placement so no node is offered a shard that Elasticsearch's allocation
+/// deciders would reject: same-shard exclusion, shards-per-node limits, allocation awareness and
+/// disk watermarks.
+pub struct Allocation<'a> {
+    awareness: Vec<String>,
+    /// Distinct values of each awareness attribute across the candidate nodes
+    awareness_values: HashMap<String, usize>,
+    cluster_shards_per_node: Option<usize>,
+    copies: HashMap<ShardId, usize>,
+    /// Projected disk of each node, for nodes with filesystem stats
+    disks: Vec<Option<Disk>>,
+    index_shards: HashMap<(usize, String), usize>,
+    node_shards: Vec<usize>,
+    nodes: Vec<&'a Node>,
+    placed: HashMap<ShardId, Vec<usize>>,
+    watermarks: Watermarks,
+}
+
+impl<'a> Allocation<'a> {
+    pub fn new(
+        nodes: Vec<&'a Node>,
+        shards: &[ShardDoc],
+        settings: &ClusterSettings,
+        // Disk of each node without the shards that will be allocated
+        disks: &HashMap<String, Disk>,
+    ) -> Self {
+        let awareness = settings.get_list("cluster.routing.allocation.awareness.attributes");
+        let awareness_values = awareness
+            .iter()
+            .map(|attribute| {
+                let values: HashSet<&str> = nodes
+                    .iter()
+                    .filter_map(|node| node.attribute(attribute))
+                    .collect();
+                (attribute.clone(), values.len())
+            })
+            .collect();
+        let cluster_shards_per_node = settings
+            .get_i64("cluster.routing.allocation.total_shards_per_node")
+            .filter(|limit| *limit > 0)
+            .map(|limit| limit as usize);
+
+        let mut copies = HashMap::new();
+        shards.iter().for_each(|shard| {
+            *copies.entry(shard_id(shard)).or_insert(0) += 1;
+        });
+
+        let threshold_enabled = settings
+            .get_bool("cluster.routing.allocation.disk.threshold_enabled")
+            .unwrap_or(true);
+        let disks = nodes
+            .iter()
+            .map(|node| disks.get(&node.name).filter(|_| threshold_enabled).copied())
+            .collect();
+
+        Self {
+            awareness,
+            awareness_values,
+            cluster_shards_per_node,
+            copies,
+            disks,
+            index_shards: HashMap::new(),
+            node_shards: vec![0; nodes.len()],
+            nodes,
+            placed: HashMap::new(),
+            watermarks: Watermarks::from(settings),
+        }
+    }
+
+    /// Count shards that are placed on the nodes but not allocated through this plan, such as
+    /// those of other tiers, towards the cluster wide shards per node limit
+    pub fn placed_elsewhere(mut self, node_shards: &HashMap<String, usize>) -> Self {
+        for (count, node) in self.node_shards.iter_mut().zip(&self.nodes) {
+            *count += node_shards.get(&node.name).copied().unwrap_or(0);
+        }
+        self
+    }
+
+    /// Whether placing the shard on the node at `node` keeps the plan valid
+    pub fn can_allocate(&self, node: usize, shard: &ShardDoc) -> bool {
+        let id = shard_id(shard);
+        let placed = self.placed.get(&id);
+
+        // Same shard: a primary and its replicas must all live on different nodes
+        if placed.is_some_and(|nodes| nodes.contains(&node)) {
+            return false;
+        }
+
+        // Shards per node limits, both index level and cluster wide
+        let index_limit = shard
+            .index()
+            .and_then(|index| index.total_shards_per_node())
+            .map(|limit| limit as usize);
+        let index_shards = self
+            .index_shards
+            .get(&(node, id.0.clone()))
+            .copied()
+            .unwrap_or(0);
+        if index_limit.is_some_and(|limit| index_shards >= limit) {
+            return false;
+        }
+        if self
+            .cluster_shards_per_node
+            .is_some_and(|limit| self.node_shards[node] >= limit)
+        {
+            return false;
+        }
+
+        // Disk watermarks: never fill a node past the low watermark
+        if let Some(disk) = &self.disks[node] {
+            let after = disk.after(0, shard.stats().size_in_bytes());
+            if self.watermarks.level(after.used, after.total) > DiskLevel::Ok {
+                return false;
+            }
+        }
+
+        // Awareness: spread copies evenly across each attribute's values
+        let copies = self.copies.get(&id).copied().unwrap_or(1);
+        self.awareness.iter().all(|attribute| {
+            let value = match self.nodes[node].attribute(attribute) {
+                Some(value) => value,
+                None => return true,
+            };
+            let values = self.awareness_values[attribute].max(1);
+            let limit = copies.div_ceil(values);
+            let same_value = placed
+                .map(|nodes| {
+                    nodes
+                        .iter()
+                        .filter(|n| self.nodes[**n].attribute(attribute) == Some(value))
+                        .count()
+                })
+                .unwrap_or(0);
+            same_value < limit
+        })
+    }
+
+    pub fn allocate(&mut self, node: usize, shard: &ShardDoc) {
+        let id = shard_id(shard);
+        *self.index_shards.entry((node, id.0.clone())).or_insert(0) += 1;
+        self.node_shards[node] += 1;
+        if let Some(disk) = &mut self.disks[node] {
+            *disk = disk.after(0, shard.stats().size_in_bytes());
+        }
+        self.placed.entry(id).or_default().push(node);
+    }
+
+    pub fn deallocate(&mut self, node: usize, shard: &ShardDoc) {
+        let id = shard_id(shard);
+        if let Some(count) = self.index_shards.get_mut(&(node, id.0.clone())) {
+            *count = count.saturating_sub(1);
+        }
+        self.node_shards[node] = self.node_shards[node].saturating_sub(1);
+        if let Some(disk) = &mut self.disks[node] {
+            *disk = disk.after(shard.stats().size_in_bytes(), 0);
+        }
+        if let Some(nodes) = self.placed.get_mut(&id) {
+            if let Some(position) = nodes.iter().position(|n| *n == node) {
+                nodes.swap_remove(position);
+            }
+        }
+    }
+
+    /// Whether the shard already placed on `from` may relocate to `to`
+    pub fn can_move(&mut self, from: usize, to: usize, shard: &ShardDoc) -> bool {
+        self.deallocate(from, shard);
+        let allowed = self.can_allocate(to, shard);
+        self.allocate(from, shard);
+        allowed
+    }
+
+    /// Whether two shards placed on different nodes may trade places
+    pub fn can_swap(&mut self, a: (usize, &ShardDoc), b: (usize, &ShardDoc)) -> bool {
+        self.deallocate(a.0, a.1);
+        self.deallocate(b.0, b.1);
+        let allowed = self.can_allocate(b.0, a.1) && {
+            self.allocate(b.0, a.1);
+            let allowed = self.can_allocate(a.0, b.1);
+            self.deallocate(b.0, a.1);
+            allowed
+        };
+        self.allocate(a.0, a.1);
+        self.allocate(b.0, b.1);
+        allowed
+    }
+}
+
+pub fn shard_id(shard: &ShardDoc) -> ShardId {
+    (shard.index_name(), shard.shard_number())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::fixtures::{cluster_settings, index, node, shard};
+    use serde_json::json;
+
+    fn nodes(zones: &[&str]) -> Vec<Node> {
+        zones
+            .iter()
+            .enumerate()
+            .map(|(n, zone)| node(&format!("node-{n}"), &["data"], json!({ "zone": zone })))
+            .collect()
+    }
+
+    #[test]
+    fn same_shard_copies_need_different_nodes() {
+        let nodes = nodes(&["a", "a"]);
+        let index = index("logs", json!({}));
+        let shards = vec![
+            shard(&index, 0, true, &nodes[0], 100),
+            shard(&index, 0, false, &nodes[1], 100),
+        ];
+        let settings = cluster_settings(json!({}));
+        let mut allocation =
+            Allocation::new(nodes.iter().collect(), &shards, &settings, &HashMap::new());
+        allocation.allocate(0, &shards[0]);
+
+        assert!(!allocation.can_allocate(0, &shards[1]));
+        assert!(allocation.can_allocate(1, &shards[1]));
+    }
+
+    #[test]
+    fn index_shards_per_node_limit() {
+        let nodes = nodes(&["a", "a"]);
+        let index = index(
+            "logs",
+            json!({ "routing": { "allocation": { "total_shards_per_node": "1" } } }),
+        );
+        let shards = vec![
+            shard(&index, 0, true, &nodes[0], 100),
+            shard(&index, 1, true, &nodes[1], 100),
+        ];
+        let settings = cluster_settings(json!({}));
+        let mut allocation =
+            Allocation::new(nodes.iter().collect(), &shards, &settings, &HashMap::new());
+        allocation.allocate(0, &shards[0]);
+
+        assert!(!allocation.can_allocate(0, &shards[1]));
+        assert!(allocation.can_allocate(1, &shards[1]));
+        // Moving the placed shard away frees the slot again
+        assert!(allocation.can_move(0, 1, &shards[0]));
+    }
+
+    #[test]
+    fn cluster_shards_per_node_limit() {
+        let nodes = nodes(&["a", "a"]);
+        let (logs, metrics) = (index("logs", json!({})), index("metrics", json!({})));
+        let shards = vec![
+            shard(&logs, 0, true, &nodes[0], 100),
+            shard(&metrics, 0, true, &nodes[1], 100),
+        ];
+        let settings = cluster_settings(json!({
+            "cluster.routing.allocation.total_shards_per_node": "1"
+        }));
+        let mut allocation =
+            Allocation::new(nodes.iter().collect(), &shards, &settings, &HashMap::new());
+        allocation.allocate(0, &shards[0]);
+
+        assert!(!allocation.can_allocate(0, &shards[1]));
+        assert!(allocation.can_allocate(1, &shards[1]));
+    }
+
+    #[test]
+    fn cluster_shards_per_node_limit_counts_shards_placed_elsewhere() {
+        let nodes = nodes(&["a", "a"]);
+        let index = index("logs", json!({}));
+        let shards = vec![shard(&index, 0, true, &nodes[0], 100)];
+        let settings = cluster_settings(json!({
+            "cluster.routing.allocation.total_shards_per_node": "2"
+        }));
+        let elsewhere = HashMap::from([("node-0".to_string(), 2), ("node-1".to_string(), 1)]);
+        let allocation =
+            Allocation::new(nodes.iter().collect(), &shards, &settings, &HashMap::new())
+                .placed_elsewhere(&elsewhere);
+
+        assert!(!allocation.can_allocate(0, &shards[0]));
+        assert!(allocation.can_allocate(1, &shards[0]));
+    }
+
+    #[test]
+    fn awareness_spreads_copies_across_zones() {
+        // Two copies over zones a and b allow at most div_ceil(2, 2) = 1 copy per zone
+        let nodes = nodes(&["a", "a", "b"]);
+        let index = index("logs", json!({}));
+        let shards = vec![
+            shard(&index, 0, true, &nodes[0], 100),
+            shard(&index, 0, false, &nodes[1], 100),
+        ];
+        let settings = cluster_settings(json!({
+            "cluster.routing.allocation.awareness.attributes": "zone"
+        }));
+        let mut allocation =
+            Allocation::new(nodes.iter().collect(), &shards, &settings, &HashMap::new());
+        allocation.allocate(0, &shards[0]);
+
+        assert!(!allocation.can_allocate(1, &shards[1]));
+        assert!(allocation.can_allocate(2, &shards[1]));
+    }
+
+    #[test]
+    fn awareness_limit_rounds_up() {
+        // Three copies over two zones allow div_ceil(3, 2) = 2 copies in one zone
+        let nodes = nodes(&["a", "a", "a", "b"]);
+        let index = index("logs", json!({ "number_of_replicas": "2" }));
+        let shards = vec![
+            shard(&index, 0, true, &nodes[0], 100),
+            shard(&index, 0, false, &nodes[1], 100),
+            shard(&index, 0, false, &nodes[2], 100),
+        ];
+        let settings = cluster_settings(json!({
+            "cluster.routing.allocation.awareness.attributes": "zone"
+        }));
+        let mut allocation =
+            Allocation::new(nodes.iter().collect(), &shards, &settings, &HashMap::new());
+        allocation.allocate(0, &shards[0]);
+        assert!(allocation.can_allocate(1, &shards[1]));
+        allocation.allocate(1, &shards[1]);
+
+        assert!(!allocation.can_allocate(2, &shards[2]));
+        assert!(allocation.can_allocate(3, &shards[2]));
+    }
+
+    #[test]
+    fn nodes_without_the_attribute_are_unconstrained() {
+        let mut nodes = nodes(&["a"]);
+        nodes.push(node("node-1", &["data"], json!({})));
+        let index = index("logs", json!({}));
+        let shards = vec![
+            shard(&index, 0, true, &nodes[0], 100),
+            shard(&index, 0, false, &nodes[1], 100),
+        ];
+        let settings = cluster_settings(json!({
+            "cluster.routing.allocation.awareness.attributes": "zone"
+        }));
+        let mut allocation =
+            Allocation::new(nodes.iter().collect(), &shards, &settings, &HashMap::new());
+        allocation.allocate(0, &shards[0]);
+
+        assert!(allocation.can_allocate(1, &shards[1]));
+    }
+}
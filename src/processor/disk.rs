@@ -0,0 +1,182 @@
+use crate::data::{ClusterSettings, NodesStats};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+/// A disk watermark, either a maximum fraction of the disk used or a minimum of free bytes
+#[derive(Clone, Copy)]
+pub enum Watermark {
+    Ratio(f64),
+    Free(u64),
+}
+
+impl Watermark {
+    /// Parse a watermark setting such as `85%`, `0.85` or `50gb`
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_lowercase();
+        if let Some(percent) = value.strip_suffix('%') {
+            return percent.parse::<f64>().ok().map(|p| Self::Ratio(p / 100.0));
+        }
+        if let Ok(ratio) = value.parse::<f64>() {
+            return Some(Self::Ratio(ratio));
+        }
+        const UNITS: [(&str, u64); 6] = [
+            ("pb", 1 << 50),
+            ("tb", 1 << 40),
+            ("gb", 1 << 30),
+            ("mb", 1 << 20),
+            ("kb", 1 << 10),
+            ("b", 1),
+        ];
+        UNITS.iter().find_map(|(unit, multiplier)| {
+            let number = value.strip_suffix(unit)?.parse::<f64>().ok()?;
+            Some(Self::Free((number * *multiplier as f64) as u64))
+        })
+    }
+
+    fn is_exceeded(&self, used: u64, total: u64) -> bool {
+        match self {
+            Self::Ratio(ratio) => used as f64 > ratio * total as f64,
+            Self::Free(free) => total.saturating_sub(used) < *free,
+        }
+    }
+}
+
+/// The disk watermarks of the cluster, at the Elasticsearch defaults unless configured
+pub struct Watermarks {
+    low: Watermark,
+    high: Watermark,
+    flood_stage: Watermark,
+}
+
+impl From<&ClusterSettings> for Watermarks {
+    fn from(settings: &ClusterSettings) -> Self {
+        let watermark = |name: &str, default: f64| {
+            settings
+                .get(&format!("cluster.routing.allocation.disk.watermark.{name}"))
+                .and_then(Value::as_str)
+                .and_then(Watermark::parse)
+                .unwrap_or(Watermark::Ratio(default))
+        };
+        Self {
+            low: watermark("low", 0.85),
+            high: watermark("high", 0.90),
+            flood_stage: watermark("flood_stage", 0.95),
+        }
+    }
+}
+
+impl Watermarks {
+    /// The highest watermark that a node with `used` of `total` bytes is past
+    pub fn level(&self, used: u64, total: u64) -> DiskLevel {
+        if self.flood_stage.is_exceeded(used, total) {
+            DiskLevel::FloodStage
+        } else if self.high.is_exceeded(used, total) {
+            DiskLevel::High
+        } else if self.low.is_exceeded(used, total) {
+            DiskLevel::Low
+        } else {
+            DiskLevel::Ok
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum DiskLevel {
+    Ok,
+    Low,
+    High,
+    FloodStage,
+}
+
+impl Display for DiskLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok => write!(f, "ok"),
+            Self::Low => write!(f, "low"),
+            Self::High => write!(f, "high"),
+            Self::FloodStage => write!(f, "flood_stage"),
+        }
+    }
+}
+
+/// Disk size and bytes used of a node, where used counts everything that is not available to
+/// Elasticsearch, as the disk allocation decider does
+#[derive(Clone, Copy)]
+pub struct Disk {
+    pub total: u64,
+    pub used: u64,
+}
+
+impl Disk {
+    /// The disk of every node with filesystem stats, keyed by node name
+    pub fn by_node(nodes_stats: &NodesStats) -> HashMap<String, Disk> {
+        nodes_stats
+            .nodes
+            .values()
+            .filter_map(|node| {
+                let (total, available) = node.disk()?;
+                let used = total.saturating_sub(available);
+                Some((node.name.clone(), Disk { total, used }))
+            })
+            .collect()
+    }
+
+    /// The disk after shards totalling `removed` bytes leave and `added` bytes arrive
+    pub fn after(&self, removed: u64, added: u64) -> Disk {
+        Disk {
+            total: self.total,
+            used: (self.used + added).saturating_sub(removed),
+        }
+    }
+
+    pub fn ratio(&self) -> f64 {
+        match self.total {
+            0 => 0.0,
+            total => self.used as f64 / total as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_percentages_as_ratios() {
+        assert!(matches!(Watermark::parse("85%"), Some(Watermark::Ratio(r)) if r == 0.85));
+        assert!(matches!(Watermark::parse(" 90.5% "), Some(Watermark::Ratio(r)) if r == 0.905));
+    }
+
+    #[test]
+    fn parses_fractions_as_ratios() {
+        assert!(matches!(Watermark::parse("0.85"), Some(Watermark::Ratio(r)) if r == 0.85));
+    }
+
+    #[test]
+    fn parses_byte_sizes_as_free_space() {
+        assert!(matches!(Watermark::parse("500gb"), Some(Watermark::Free(b)) if b == 500 << 30));
+        assert!(matches!(Watermark::parse("1.5TB"), Some(Watermark::Free(b)) if b == 3 << 39));
+        assert!(matches!(
+            Watermark::parse("100b"),
+            Some(Watermark::Free(100))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        for value in ["", "high", "gb", "85%%", "500 gib"] {
+            assert!(
+                Watermark::parse(value).is_none(),
+                "{value} should not parse"
+            );
+        }
+    }
+
+    #[test]
+    fn free_space_watermarks_are_exceeded_below_the_free_bytes() {
+        let watermark = Watermark::parse("500gb").unwrap();
+        assert!(!watermark.is_exceeded(500 << 30, 1000 << 30));
+        assert!(watermark.is_exceeded((500 << 30) + 1, 1000 << 30));
+    }
+}
@@ -0,0 +1,95 @@
+//! Nodes, shards and settings for unit tests, built through the same deserializers as real
+//! API responses
+
+use crate::data::{ClusterSettings, IndexSettings, Node, ShardDoc, ShardEnrich, ShardStats};
+use serde_json::{json, Value};
+
+pub fn node(name: &str, roles: &[&str], attributes: Value) -> Node {
+    serde_json::from_value(json!({
+        "attributes": attributes,
+        "build_flavor": "default",
+        "build_hash": "test",
+        "build_type": "docker",
+        "host": name,
+        "ip": "127.0.0.1",
+        "name": name,
+        "os": { "allocated_processors": 4 },
+        "jvm": { "mem": { "heap_max_in_bytes": 1u64 << 30 } },
+        "roles": roles,
+        "version": "8.15.0",
+    }))
+    .expect("Invalid test node")
+}
+
+pub fn index(name: &str, settings: Value) -> IndexSettings {
+    let mut index = json!({
+        "creation_date": "1",
+        "number_of_replicas": "1",
+        "number_of_shards": "1",
+        "provided_name": name,
+        "uuid": format!("{name}-uuid"),
+        "version": { "created": "8500000" },
+    });
+    if let (Some(index), Value::Object(settings)) = (index.as_object_mut(), settings) {
+        index.extend(settings);
+    }
+    let index: IndexSettings = serde_json::from_value(index).expect("Invalid test index");
+    index.with_name(name)
+}
+
+pub fn shard(
+    index: &IndexSettings,
+    number: u16,
+    primary: bool,
+    node: &Node,
+    bytes: u64,
+) -> ShardDoc {
+    let stats: ShardStats = serde_json::from_value(json!({
+        "docs": { "count": 1, "deleted": 0, "total_size_in_bytes": bytes },
+        "store": { "size_in_bytes": bytes, "reserved_in_bytes": 0 },
+        "indexing": {
+            "index_total": 0,
+            "index_time_in_millis": 0,
+            "index_current": 0,
+            "index_failed": 0,
+            "delete_total": 0,
+            "delete_time_in_millis": 0,
+            "delete_current": 0,
+            "noop_update_total": 0,
+            "is_throttled": false,
+            "throttle_time_in_millis": 0,
+            "write_load": 0.0,
+        },
+        "search": {
+            "open_contexts": 0,
+            "query_total": 0,
+            "query_time_in_millis": 0,
+            "query_current": 0,
+            "fetch_total": 0,
+            "fetch_time_in_millis": 0,
+            "fetch_current": 0,
+            "scroll_total": 0,
+            "scroll_time_in_millis": 0,
+            "scroll_current": 0,
+            "suggest_total": 0,
+            "suggest_time_in_millis": 0,
+            "suggest_current": 0,
+        },
+        "routing": {
+            "node": format!("{}-id", node.name),
+            "primary": primary,
+            "relocating_node": null,
+            "state": "STARTED",
+        },
+    }))
+    .expect("Invalid test shard stats");
+    let enrich = ShardEnrich {
+        index: Some(index.clone()),
+        node: Some(node.clone()),
+    };
+    ShardDoc::new(number, stats, enrich, 0)
+}
+
+pub fn cluster_settings(persistent: Value) -> ClusterSettings {
+    serde_json::from_value(json!({ "persistent": persistent })).expect("Invalid test settings")
+}
@@ -5,11 +5,14 @@ use color_eyre::eyre::Result;
 pub fn extract_shard_docs(
     mut indices_stats: IndicesStats,
     lookups: &Lookups,
+    timestamp: i64,
 ) -> Result<Vec<ShardDoc>> {
     let shard_docs: Vec<ShardDoc> = indices_stats
         .indices
         .drain()
-        .flat_map(|(index, index_stats)| extract_index_stats(index, index_stats, lookups))
+        .flat_map(|(index, index_stats)| {
+            extract_index_stats(index, index_stats, lookups, timestamp)
+        })
         .collect();
 
     Ok(shard_docs)
@@ -19,6 +22,7 @@ fn extract_index_stats(
     index_name: String,
     mut index_stats: IndexStats,
     lookups: &Lookups,
+    timestamp: i64,
 ) -> Vec<ShardDoc> {
     let data_stream = lookups.data_stream.by_id(&index_name).cloned();
     let enrich = ShardEnrich {
@@ -34,7 +38,13 @@ fn extract_index_stats(
         .shards
         .drain()
         .flat_map(|(shard_number, shards_stats)| {
-            extract_shard_stats(shard_number, shards_stats, enrich.clone(), lookups)
+            extract_shard_stats(
+                shard_number,
+                shards_stats,
+                enrich.clone(),
+                lookups,
+                timestamp,
+            )
         })
         .collect()
 }
@@ -44,6 +54,7 @@ fn extract_shard_stats(
     mut shards_stats: Vec<ShardStats>,
     mut enrich: ShardEnrich,
     lookups: &Lookups,
+    timestamp: i64,
 ) -> Vec<ShardDoc> {
     shards_stats
         .drain(..)
@@ -56,7 +67,12 @@ fn extract_shard_stats(
                     .cloned()
                     .expect("Node not found"),
             );
-            Some(ShardDoc::new(number, shard_stats, enrich.clone()))
+            Some(ShardDoc::new(
+                number,
+                shard_stats,
+                enrich.clone(),
+                timestamp,
+            ))
         })
         .collect()
 }
@@ -1,5 +1,5 @@
 use super::{Lookup, LookupDisplay};
-use crate::data::{DataStream, DataStreams, Indices};
+use crate::data::{DataStream, DataStreams};
 
 impl From<DataStreams> for Lookup<DataStream> {
     fn from(mut data_streams: DataStreams) -> Self {
@@ -9,11 +9,20 @@ impl From<DataStreams> for Lookup<DataStream> {
             .drain(..)
             .for_each(|mut data_stream| {
                 let name = data_stream.name.clone();
-                let indices: Indices = data_stream.indices.drain(..).collect();
+                // The last backing index is the current write generation
+                data_stream.write_index = data_stream
+                    .indices
+                    .last()
+                    .map(|index| index.index_name.clone());
+                let indices: Vec<String> = data_stream
+                    .indices
+                    .iter()
+                    .map(|index| index.index_name.clone())
+                    .collect();
                 lookup.add(data_stream).with_name(&name);
                 // Each data stream can have multiple indices
                 indices.iter().for_each(|index| {
-                    lookup.with_id(&index.index_name.clone());
+                    lookup.with_id(index);
                 });
             });
         lookup
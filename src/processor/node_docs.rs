@@ -0,0 +1,26 @@
+use crate::data::{Node, NodeDoc, ShardDoc};
+use std::collections::HashMap;
+
+/// Total up the shards of every node under the current and desired placements
+pub fn extract_node_docs(nodes: &[Node], shards: &[ShardDoc], timestamp: i64) -> Vec<NodeDoc> {
+    let mut node_docs: Vec<NodeDoc> = nodes
+        .iter()
+        .map(|node| NodeDoc::new(node.clone(), timestamp))
+        .collect();
+    node_docs.sort_unstable_by(|a, b| a.name().cmp(b.name()));
+    let by_name: HashMap<String, usize> = node_docs
+        .iter()
+        .enumerate()
+        .map(|(i, doc)| (doc.name().to_string(), i))
+        .collect();
+
+    for shard in shards {
+        if let Some(i) = shard.node_name().and_then(|name| by_name.get(name)) {
+            node_docs[*i].add_current(shard);
+        }
+        if let Some(i) = shard.desired_node().and_then(|name| by_name.get(name)) {
+            node_docs[*i].add_desired(shard);
+        }
+    }
+    node_docs
+}
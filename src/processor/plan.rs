@@ -0,0 +1,317 @@
+use super::tiers::Tier;
+use super::{Evaluation, StrategyType};
+use crate::data::{BalanceScore, ShardDoc, ShardMove, SummaryDoc};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+/// Shard totals for one node under a given placement
+#[derive(Clone, Copy, Default)]
+pub struct NodeTotals {
+    pub bytes: u64,
+    pub shards: usize,
+    pub write_load: f64,
+}
+
+impl NodeTotals {
+    fn add(&mut self, shard: &ShardDoc) {
+        self.bytes += shard.stats().size_in_bytes();
+        self.shards += 1;
+        self.write_load += shard.stats().write_load();
+    }
+}
+
+/// The moves needed to reach the desired placement, with per-node current and desired totals
+pub struct Plan {
+    pub moves: Vec<ShardMove>,
+    nodes: BTreeMap<String, (NodeTotals, NodeTotals)>,
+    scores: (BalanceScore, BalanceScore),
+    shards: usize,
+    timestamp: i64,
+}
+
+impl Plan {
+    pub fn bytes(&self) -> u64 {
+        self.moves.iter().map(|shard_move| shard_move.bytes).sum()
+    }
+
+    /// Current and desired totals of each node, by node name
+    pub fn nodes(&self) -> impl Iterator<Item = (&String, &NodeTotals, &NodeTotals)> {
+        self.nodes
+            .iter()
+            .map(|(name, (current, desired))| (name, current, desired))
+    }
+
+    /// Balance scores of the current and desired placements
+    pub fn scores(&self) -> (BalanceScore, BalanceScore) {
+        self.scores
+    }
+
+    /// Summarize the balance of the current and desired placements
+    pub fn summary(&self, strategy: StrategyType) -> SummaryDoc {
+        let (current, desired) = self.scores();
+        SummaryDoc::new(self.timestamp, strategy.to_string())
+            .with_scores(self.nodes.len(), current, desired)
+            .with_moves(self.shards, self.moves.len(), self.bytes())
+    }
+}
+
+/// How evenly shards, bytes and write load are spread across the nodes, each node's totals
+/// divided by its capacity
+fn score<'a>(totals: impl Iterator<Item = (&'a NodeTotals, f64)>) -> BalanceScore {
+    let (mut shards, mut bytes, mut write_load) = (Vec::new(), Vec::new(), Vec::new());
+    for (node, capacity) in totals {
+        shards.push(node.shards as f64 / capacity);
+        bytes.push(node.bytes as f64 / capacity);
+        write_load.push(node.write_load / capacity);
+    }
+    BalanceScore {
+        shards: BalanceScore::coefficient_of_variation(&shards),
+        bytes: BalanceScore::coefficient_of_variation(&bytes),
+        write_load: BalanceScore::coefficient_of_variation(&write_load),
+    }
+}
+
+/// Score each tier across its own nodes, since shards never move between tiers, and average the
+/// tier scores weighted by their shard counts
+fn tier_scores(shards: &[ShardDoc], tiers: &[Tier]) -> Option<(BalanceScore, BalanceScore)> {
+    let (mut current, mut desired) = (BalanceScore::default(), BalanceScore::default());
+    let mut weights = 0.0;
+    for tier in tiers.iter().filter(|tier| !tier.shards.is_empty()) {
+        let mut nodes: BTreeMap<&str, (NodeTotals, NodeTotals)> = tier
+            .nodes
+            .iter()
+            .map(|(name, _)| (name.as_str(), Default::default()))
+            .collect();
+        for shard in tier.shards.iter().map(|i| &shards[*i]) {
+            if let Some(totals) = shard.node_name().and_then(|node| nodes.get_mut(node)) {
+                totals.0.add(shard);
+            }
+            if let Some(totals) = shard.desired_node().and_then(|node| nodes.get_mut(node)) {
+                totals.1.add(shard);
+            }
+        }
+        let capacities = tier.nodes.iter().map(|(_, capacity)| *capacity);
+        let weight = tier.shards.len() as f64;
+        current.add_weighted(
+            &score(nodes.values().map(|n| &n.0).zip(capacities.clone())),
+            weight,
+        );
+        desired.add_weighted(&score(nodes.values().map(|n| &n.1).zip(capacities)), weight);
+        weights += weight;
+    }
+    match weights > 0.0 {
+        true => Some((current.scaled(1.0 / weights), desired.scaled(1.0 / weights))),
+        false => None,
+    }
+}
+
+impl From<&Evaluation> for Plan {
+    fn from(evaluation: &Evaluation) -> Self {
+        let shards = evaluation.shards.as_slice();
+        // Start from every node of a tier, so empty nodes show up and count against the balance
+        let mut nodes: BTreeMap<String, (NodeTotals, NodeTotals)> = evaluation
+            .tiers
+            .iter()
+            .flat_map(|tier| tier.nodes.iter())
+            .map(|(name, _)| (name.clone(), Default::default()))
+            .collect();
+        for shard in shards {
+            if let Some(current) = shard.node_name() {
+                nodes.entry(current.to_string()).or_default().0.add(shard);
+            }
+            if let Some(desired) = shard.desired_node() {
+                nodes.entry(desired.to_string()).or_default().1.add(shard);
+            }
+        }
+        let scores = tier_scores(shards, &evaluation.tiers).unwrap_or_else(|| {
+            (
+                score(nodes.values().map(|(current, _)| (current, 1.0))),
+                score(nodes.values().map(|(_, desired)| (desired, 1.0))),
+            )
+        });
+        Self {
+            moves: plan_moves(shards),
+            nodes,
+            scores,
+            shards: shards.len(),
+            timestamp: shards
+                .iter()
+                .map(ShardDoc::timestamp)
+                .max()
+                .unwrap_or_else(|| chrono::Utc::now().timestamp_millis()),
+        }
+    }
+}
+
+/// Diff each shard's current node against its desired node to build the moves to enforce
+pub fn plan_moves(shards: &[ShardDoc]) -> Vec<ShardMove> {
+    let mut moves: Vec<ShardMove> = shards
+        .iter()
+        .filter_map(|shard| {
+            let from_node = shard.node_name()?;
+            let to_node = shard.desired_node()?;
+            if from_node == to_node {
+                return None;
+            }
+            // Relocating or initializing shards cannot be moved again
+            if shard.state() != "STARTED" {
+                log::debug!(
+                    "Skipping {}[{}] in state {}",
+                    shard.index_name(),
+                    shard.shard_number(),
+                    shard.state()
+                );
+                return None;
+            }
+            Some(ShardMove {
+                index: shard.index_name(),
+                shard: shard.shard_number(),
+                primary: shard.primary(),
+                bytes: shard.stats().size_in_bytes(),
+                from_node: from_node.to_string(),
+                to_node: to_node.to_string(),
+            })
+        })
+        .collect();
+    moves.sort_by(|a, b| {
+        a.index
+            .cmp(&b.index)
+            .then(a.shard.cmp(&b.shard))
+            .then(b.primary.cmp(&a.primary))
+    });
+    moves
+}
+
+/// Format a byte count with binary units, e.g. `1.5 GiB`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{value:.1} {}", UNITS[unit]),
+    }
+}
+
+/// Write rows as aligned columns under a header, the first column left-aligned and the rest
+/// right-aligned
+pub fn write_table<const N: usize>(
+    f: &mut Formatter<'_>,
+    header: [&str; N],
+    rows: &[[String; N]],
+) -> fmt::Result {
+    let header = header.map(String::from);
+    let mut widths = header.clone().map(|column| column.len());
+    rows.iter().for_each(|row| {
+        row.iter()
+            .enumerate()
+            .for_each(|(i, cell)| widths[i] = widths[i].max(cell.len()))
+    });
+
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        for (i, cell) in row.iter().enumerate() {
+            match i {
+                0 => write!(f, "{:<w$}", cell, w = widths[i])?,
+                _ => write!(f, "  {:>w$}", cell, w = widths[i])?,
+            }
+        }
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let rows: Vec<[String; 4]> = self
+            .nodes
+            .iter()
+            .map(|(name, (current, desired))| {
+                [
+                    name.clone(),
+                    format!("{} -> {}", current.shards, desired.shards),
+                    format!(
+                        "{} -> {}",
+                        format_bytes(current.bytes),
+                        format_bytes(desired.bytes)
+                    ),
+                    format!("{:.4} -> {:.4}", current.write_load, desired.write_load),
+                ]
+            })
+            .collect();
+        write_table(f, ["NODE", "SHARDS", "BYTES", "WRITE LOAD"], &rows)?;
+
+        writeln!(
+            f,
+            "\n{} moves relocating {}",
+            self.moves.len(),
+            format_bytes(self.bytes())
+        )?;
+        for shard_move in &self.moves {
+            writeln!(f, "  {shard_move}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::fixtures::{index, node, shard};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    /// Plan shards of the given sizes, one index each, that stay where they are
+    fn plan(placement: &[(&str, u64)], tier: Vec<(String, f64)>) -> Plan {
+        let shards = placement
+            .iter()
+            .enumerate()
+            .map(|(i, (name, bytes))| {
+                let index = index(&format!("index-{i}"), json!({}));
+                let mut shard = shard(
+                    &index,
+                    0,
+                    true,
+                    &node(name, &["data_hot"], json!({})),
+                    *bytes,
+                );
+                shard.set_desired_node(name.to_string());
+                shard
+            })
+            .collect::<Vec<_>>();
+        let tiers = vec![Tier {
+            nodes: tier,
+            shards: (0..shards.len()).collect(),
+        }];
+        Plan::from(&Evaluation {
+            shards,
+            nodes: Vec::new(),
+            disks: HashMap::new(),
+            tiers,
+        })
+    }
+
+    #[test]
+    fn empty_nodes_count_against_the_balance() {
+        let tier = vec![(String::from("a"), 1.0), (String::from("b"), 1.0)];
+        let plan = plan(&[("a", 100), ("a", 100)], tier);
+
+        assert_eq!(plan.nodes().count(), 2);
+        let (current, desired) = plan.scores();
+        assert_eq!(current.bytes, 1.0);
+        assert_eq!(desired.shards, 1.0);
+    }
+
+    #[test]
+    fn scores_are_relative_to_capacity() {
+        let tier = vec![(String::from("a"), 2.0), (String::from("b"), 1.0)];
+        let plan = plan(&[("a", 100), ("a", 100), ("b", 100)], tier);
+
+        let (current, _) = plan.scores();
+        assert_eq!(current.bytes, 0.0);
+        assert_eq!(current.shards, 0.0);
+    }
+}
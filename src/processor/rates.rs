@@ -0,0 +1,187 @@
+use crate::data::{ShardDoc, ShardRates};
+use std::collections::HashMap;
+
+/// Identifies a shard copy across snapshots: index UUID, shard number and node ID. Replicas of a
+/// shard share everything but the node, and a relocated copy restarts its counters anyway.
+type ShardKey = (String, u16, String);
+
+/// Identifies a shard copy by role rather than location: index UUID, shard number and whether
+/// it is the primary. Replicas of a shard are interchangeable, so they share one entry.
+type CopyKey = (String, u16, bool);
+
+/// The cumulative counters of a shard at the time of a snapshot
+struct Counters {
+    timestamp: i64,
+    index_total: u64,
+    index_time_in_millis: u64,
+    query_total: u64,
+    fetch_total: u64,
+}
+
+impl From<&ShardDoc> for Counters {
+    fn from(shard: &ShardDoc) -> Self {
+        let stats = shard.stats();
+        Self {
+            timestamp: shard.timestamp(),
+            index_total: stats.index_total(),
+            index_time_in_millis: stats.index_time_in_millis(),
+            query_total: stats.query_total(),
+            fetch_total: stats.fetch_total(),
+        }
+    }
+}
+
+impl Counters {
+    /// Per-second rates since an earlier snapshot. Counters restart when a shard is relocated or
+    /// recovered, so a decrease means there is no comparable history.
+    fn rates_since(&self, previous: &Counters) -> Option<ShardRates> {
+        let elapsed_ms = self.timestamp - previous.timestamp;
+        if elapsed_ms <= 0
+            || self.index_total < previous.index_total
+            || self.index_time_in_millis < previous.index_time_in_millis
+            || self.query_total < previous.query_total
+            || self.fetch_total < previous.fetch_total
+        {
+            return None;
+        }
+        let seconds = elapsed_ms as f64 / 1000.0;
+        Some(ShardRates {
+            index_per_sec: (self.index_total - previous.index_total) as f64 / seconds,
+            query_per_sec: (self.query_total - previous.query_total) as f64 / seconds,
+            fetch_per_sec: (self.fetch_total - previous.fetch_total) as f64 / seconds,
+            // Milliseconds spent indexing per elapsed millisecond is the average number of
+            // indexing threads busy, the same unit as the cumulative `write_load`
+            write_load: (self.index_time_in_millis - previous.index_time_in_millis) as f64
+                / elapsed_ms as f64,
+        })
+    }
+}
+
+/// Keeps the previous snapshot of every shard so successive evaluations can report how busy each
+/// shard is now, rather than over its whole lifetime.
+#[derive(Default)]
+pub struct RateTracker {
+    previous: HashMap<ShardKey, Counters>,
+    /// The latest rates of each copy, carried over while its counters restart after a move
+    rates: HashMap<CopyKey, ShardRates>,
+}
+
+impl RateTracker {
+    /// Set the rates of each shard seen in the previous snapshot, then remember this snapshot. A
+    /// copy that was relocated or recovered since keeps the rates it had before.
+    pub fn update(&mut self, shards: &mut [ShardDoc]) {
+        let mut current = HashMap::with_capacity(shards.len());
+        let mut rates = HashMap::with_capacity(shards.len());
+        let (mut rated, mut carried) = (0, 0);
+        for shard in shards.iter_mut() {
+            let Some(uuid) = shard.index().map(|index| index.uuid.clone()) else {
+                continue;
+            };
+            let key = (
+                uuid.clone(),
+                shard.shard_number(),
+                shard.node_id().to_string(),
+            );
+            let copy = (uuid, shard.shard_number(), shard.primary());
+            let counters = Counters::from(&*shard);
+            let shard_rates = match self
+                .previous
+                .get(&key)
+                .and_then(|previous| counters.rates_since(previous))
+            {
+                Some(shard_rates) => Some(shard_rates),
+                None => {
+                    let last = self.rates.get(&copy).copied();
+                    carried += usize::from(last.is_some());
+                    last
+                }
+            };
+            if let Some(shard_rates) = shard_rates {
+                shard.set_rates(shard_rates);
+                rates.insert(copy, shard_rates);
+                rated += 1;
+            }
+            current.insert(key, counters);
+        }
+        log::info!(
+            "Calculated rates for {rated} of {} shards, {carried} carried over from before a move",
+            shards.len()
+        );
+        self.previous = current;
+        self.rates = rates;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::fixtures::{index, node, shard};
+    use serde_json::json;
+
+    fn counters(timestamp: i64, total: u64) -> Counters {
+        Counters {
+            timestamp,
+            index_total: total,
+            index_time_in_millis: total,
+            query_total: total,
+            fetch_total: total,
+        }
+    }
+
+    #[test]
+    fn rates_are_per_second_since_the_previous_snapshot() {
+        let rates = counters(12_000, 600).rates_since(&counters(2_000, 100));
+        let rates = rates.expect("Rates over ten seconds");
+        assert_eq!(rates.index_per_sec, 50.0);
+        assert_eq!(rates.query_per_sec, 50.0);
+        assert_eq!(rates.fetch_per_sec, 50.0);
+        assert_eq!(rates.write_load, 0.05);
+    }
+
+    #[test]
+    fn no_rates_without_elapsed_time() {
+        assert!(counters(2_000, 600)
+            .rates_since(&counters(2_000, 100))
+            .is_none());
+        assert!(counters(1_000, 600)
+            .rates_since(&counters(2_000, 100))
+            .is_none());
+    }
+
+    #[test]
+    fn no_rates_after_a_counter_reset() {
+        assert!(counters(12_000, 50)
+            .rates_since(&counters(2_000, 100))
+            .is_none());
+        let mut reset = counters(12_000, 600);
+        reset.fetch_total = 0;
+        assert!(reset.rates_since(&counters(2_000, 100)).is_none());
+    }
+
+    #[test]
+    fn relocated_copies_keep_their_rates() {
+        let index = index("logs", json!({}));
+        let (from, to) = (
+            node("node-0", &["data"], json!({})),
+            node("node-1", &["data"], json!({})),
+        );
+        let rates = ShardRates {
+            index_per_sec: 10.0,
+            query_per_sec: 2.0,
+            fetch_per_sec: 1.0,
+            write_load: 0.5,
+        };
+        let mut tracker = RateTracker::default();
+        tracker.update(&mut [shard(&index, 0, true, &from, 100)]);
+        tracker.rates.insert((index.uuid.clone(), 0, true), rates);
+
+        // The copy now lives on another node, so it has no counters to compare with
+        let mut shards = [
+            shard(&index, 0, true, &to, 100),
+            shard(&index, 0, false, &from, 100),
+        ];
+        tracker.update(&mut shards);
+        assert_eq!(shards[0].rates().map(|r| r.write_load), Some(0.5));
+        assert!(shards[1].rates().is_none());
+    }
+}
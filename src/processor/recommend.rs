@@ -0,0 +1,196 @@
+use super::lookup::Lookups;
+use super::plan::write_table;
+use super::tiers;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+/// Primary shard count for the next generation of the data streams sharing an index template
+pub struct Recommendation {
+    pub template: String,
+    data_streams: Vec<String>,
+    tier: &'static str,
+    nodes: u64,
+    replicas: u64,
+    current: u64,
+    pub recommended: u64,
+}
+
+impl Recommendation {
+    pub fn is_change(&self) -> bool {
+        self.current != self.recommended
+    }
+}
+
+/// Recommendations for every index template that backs a data stream, sorted by template name
+pub struct Recommendations(pub Vec<Recommendation>);
+
+impl Recommendations {
+    pub fn changes(&self) -> impl Iterator<Item = &Recommendation> {
+        self.0
+            .iter()
+            .filter(|recommendation| recommendation.is_change())
+    }
+}
+
+/// Recommend a primary shard count for each data stream template so that all copies of the
+/// next write index divide evenly across the nodes of its tier. Templates shared by several
+/// data streams use the largest shard and replica counts among their write indices.
+pub fn recommend_shard_counts(lookups: &Lookups) -> Recommendations {
+    let nodes = lookups.node.get_entries();
+    let mut templates: BTreeMap<String, Recommendation> = BTreeMap::new();
+    for data_stream in lookups.data_stream.get_entries() {
+        // System data stream templates are managed by Elasticsearch
+        if data_stream.system == Some(true) {
+            continue;
+        }
+        let Some(index) = data_stream
+            .write_index
+            .as_ref()
+            .and_then(|write_index| lookups.index.by_name(write_index))
+            .map(|index| index.clone().with_data_stream(Some(data_stream.clone())))
+        else {
+            log::debug!("No write index settings for {}", data_stream.name);
+            continue;
+        };
+        let Some(tier) = tiers::index_tier(&index, nodes) else {
+            log::debug!("No tier nodes for {}", data_stream.name);
+            continue;
+        };
+        let tier_nodes = tiers::tier_nodes(nodes, tier).len() as u64;
+        let shards = index.number_of_shards().unwrap_or(1).max(1) as u64;
+        let replicas = index.number_of_replicas().unwrap_or(1).max(0) as u64;
+
+        let recommendation = templates
+            .entry(data_stream.template.clone())
+            .or_insert_with(|| Recommendation {
+                template: data_stream.template.clone(),
+                data_streams: Vec::new(),
+                tier,
+                nodes: tier_nodes,
+                replicas,
+                current: shards,
+                recommended: shards,
+            });
+        recommendation.data_streams.push(data_stream.name.clone());
+        recommendation.current = recommendation.current.max(shards);
+        recommendation.replicas = recommendation.replicas.max(replicas);
+    }
+
+    let recommendations = templates
+        .into_values()
+        .map(|recommendation| Recommendation {
+            recommended: even_primaries(
+                recommendation.current,
+                recommendation.replicas,
+                recommendation.nodes,
+            ),
+            ..recommendation
+        })
+        .collect();
+    Recommendations(recommendations)
+}
+
+/// The primary count nearest to `current` whose copies divide evenly across `nodes`. Every
+/// multiple of `nodes / gcd(nodes, replicas + 1)` primaries does, so round to the nearest one,
+/// preferring more shards on a tie.
+fn even_primaries(current: u64, replicas: u64, nodes: u64) -> u64 {
+    if nodes == 0 {
+        return current;
+    }
+    let step = nodes / gcd(nodes, replicas + 1);
+    let lower = current / step * step;
+    let upper = lower + step;
+    match lower {
+        lower if lower == current => current,
+        lower if lower > 0 && current - lower < upper - current => lower,
+        _ => upper,
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b),
+    }
+}
+
+impl Display for Recommendations {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let rows: Vec<[String; 5]> = self
+            .0
+            .iter()
+            .map(|recommendation| {
+                [
+                    recommendation.template.clone(),
+                    recommendation.data_streams.len().to_string(),
+                    format!("{} {}", recommendation.nodes, recommendation.tier),
+                    recommendation.replicas.to_string(),
+                    format!(
+                        "{} -> {}",
+                        recommendation.current, recommendation.recommended
+                    ),
+                ]
+            })
+            .collect();
+        write_table(
+            f,
+            ["TEMPLATE", "DATA STREAMS", "NODES", "REPLICAS", "PRIMARIES"],
+            &rows,
+        )?;
+        writeln!(
+            f,
+            "\n{} of {} templates should change their shard count",
+            self.changes().count(),
+            self.0.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcd_of_counts() {
+        assert_eq!(gcd(6, 4), 2);
+        assert_eq!(gcd(4, 6), 2);
+        assert_eq!(gcd(5, 3), 1);
+        assert_eq!(gcd(3, 0), 3);
+    }
+
+    #[test]
+    fn keeps_counts_that_already_divide_evenly() {
+        // 3 primaries with 1 replica are 6 copies, two on each of 3 nodes
+        assert_eq!(even_primaries(3, 1, 3), 3);
+        // With 1 replica on 4 nodes, every even primary count divides evenly
+        assert_eq!(even_primaries(2, 1, 4), 2);
+    }
+
+    #[test]
+    fn rounds_to_the_nearest_even_count() {
+        // Copies of 1 replica over 6 nodes divide evenly for multiples of 3 primaries
+        assert_eq!(even_primaries(4, 1, 6), 3);
+        assert_eq!(even_primaries(5, 1, 6), 6);
+        assert_eq!(even_primaries(8, 0, 5), 10);
+        assert_eq!(even_primaries(7, 0, 5), 5);
+    }
+
+    #[test]
+    fn prefers_more_shards_on_a_tie() {
+        // Without replicas on 4 nodes, 6 primaries are as far from 4 as from 8
+        assert_eq!(even_primaries(6, 0, 4), 8);
+        // With 1 replica on 4 nodes, 3 primaries are as far from 2 as from 4
+        assert_eq!(even_primaries(3, 1, 4), 4);
+    }
+
+    #[test]
+    fn never_recommends_zero_primaries() {
+        assert_eq!(even_primaries(1, 0, 6), 6);
+        assert_eq!(even_primaries(1, 2, 3), 1);
+    }
+
+    #[test]
+    fn keeps_the_count_without_nodes() {
+        assert_eq!(even_primaries(3, 1, 0), 3);
+    }
+}
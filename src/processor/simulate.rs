@@ -0,0 +1,163 @@
+use super::disk::{Disk, DiskLevel, Watermarks};
+use super::plan::{format_bytes, write_table, Plan};
+use super::StrategyType;
+use crate::data::ClusterSettings;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter};
+
+/// The balance a plan would reach, worked out on the in-memory model without moving shards
+pub struct Simulation {
+    pub strategy: StrategyType,
+    pub plan: Plan,
+    /// Disk of each node before and after the plan, for nodes with filesystem stats
+    disks: BTreeMap<String, (Disk, Disk)>,
+    watermarks: Watermarks,
+}
+
+impl Simulation {
+    pub fn new(
+        strategy: StrategyType,
+        plan: Plan,
+        disks: &HashMap<String, Disk>,
+        cluster_settings: &ClusterSettings,
+    ) -> Self {
+        let disks = plan
+            .nodes()
+            .filter_map(|(name, current, desired)| {
+                let disk = disks.get(name)?;
+                Some((
+                    name.clone(),
+                    (*disk, disk.after(current.bytes, desired.bytes)),
+                ))
+            })
+            .collect();
+        Self {
+            strategy,
+            plan,
+            disks,
+            watermarks: Watermarks::from(cluster_settings),
+        }
+    }
+
+    /// Nodes that would be past the low watermark after the plan
+    pub fn nodes_over_watermark(&self) -> usize {
+        self.disks
+            .values()
+            .filter(|(_, after)| self.level(after) > DiskLevel::Ok)
+            .count()
+    }
+
+    fn level(&self, disk: &Disk) -> DiskLevel {
+        self.watermarks.level(disk.used, disk.total)
+    }
+}
+
+impl Display for Simulation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let rows: Vec<[String; 6]> = self
+            .plan
+            .nodes()
+            .map(|(name, _, desired)| {
+                let (disk, watermark) = match self.disks.get(name) {
+                    Some((before, after)) => (
+                        format!(
+                            "{:.1}% -> {:.1}%",
+                            100.0 * before.ratio(),
+                            100.0 * after.ratio()
+                        ),
+                        format!("{} -> {}", self.level(before), self.level(after)),
+                    ),
+                    None => ("-".to_string(), "-".to_string()),
+                };
+                [
+                    name.clone(),
+                    desired.shards.to_string(),
+                    format_bytes(desired.bytes),
+                    format!("{:.4}", desired.write_load),
+                    disk,
+                    watermark,
+                ]
+            })
+            .collect();
+        write_table(
+            f,
+            ["NODE", "SHARDS", "BYTES", "WRITE LOAD", "DISK", "WATERMARK"],
+            &rows,
+        )?;
+
+        let (before, after) = self.plan.scores();
+        let scores = [
+            ("shards", before.shards, after.shards),
+            ("bytes", before.bytes, after.bytes),
+            ("write load", before.write_load, after.write_load),
+        ]
+        .map(|(name, before, after)| {
+            [
+                name.to_string(),
+                format!("{before:.4}"),
+                format!("{after:.4}"),
+            ]
+        });
+        writeln!(f)?;
+        write_table(f, ["BALANCE SCORE", "BEFORE", "AFTER"], &scores)?;
+        write!(
+            f,
+            "\n{} strategy: {} moves relocating {}",
+            self.strategy,
+            self.plan.moves.len(),
+            format_bytes(self.plan.bytes())
+        )?;
+        match self.disks.is_empty() {
+            true => writeln!(f, ", disk usage unknown"),
+            false => writeln!(
+                f,
+                ", {} nodes past the low watermark",
+                self.nodes_over_watermark()
+            ),
+        }
+    }
+}
+
+/// Simulations of the same cluster under different strategies, side by side
+pub struct Comparison(pub Vec<Simulation>);
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let rows: Vec<[String; 7]> = self
+            .0
+            .iter()
+            .map(|simulation| {
+                let (_, after) = simulation.plan.scores();
+                [
+                    simulation.strategy.to_string(),
+                    simulation.plan.moves.len().to_string(),
+                    format_bytes(simulation.plan.bytes()),
+                    format!("{:.4}", after.shards),
+                    format!("{:.4}", after.bytes),
+                    format!("{:.4}", after.write_load),
+                    match simulation.disks.is_empty() {
+                        true => "-".to_string(),
+                        false => simulation.nodes_over_watermark().to_string(),
+                    },
+                ]
+            })
+            .collect();
+        write_table(
+            f,
+            [
+                "STRATEGY",
+                "MOVES",
+                "RELOCATING",
+                "SHARDS",
+                "BYTES",
+                "WRITE LOAD",
+                "PAST WATERMARK",
+            ],
+            &rows,
+        )?;
+        writeln!(
+            f,
+            "\nBalance scores are after the plan, where 0 is a perfect balance"
+        )
+    }
+}
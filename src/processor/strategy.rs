@@ -0,0 +1,134 @@
+use crate::data::ShardDoc;
+use clap::ValueEnum;
+
+/// The resources a shard consumes on whichever node hosts it
+#[derive(Clone, Copy, Default)]
+pub struct ShardLoad {
+    pub search: f64,
+    pub size_in_bytes: f64,
+    pub write_load: f64,
+}
+
+impl ShardLoad {
+    pub fn add(&mut self, other: &ShardLoad) {
+        self.search += other.search;
+        self.size_in_bytes += other.size_in_bytes;
+        self.write_load += other.write_load;
+    }
+
+    /// Each resource as a fraction of the cluster total, so they can be compared
+    pub fn share_of(&self, totals: &ShardLoad) -> ShardLoad {
+        let ratio = |value: f64, total: f64| match total > 0.0 {
+            true => value / total,
+            false => 0.0,
+        };
+        ShardLoad {
+            search: ratio(self.search, totals.search),
+            size_in_bytes: ratio(self.size_in_bytes, totals.size_in_bytes),
+            write_load: ratio(self.write_load, totals.write_load),
+        }
+    }
+}
+
+impl From<&ShardDoc> for ShardLoad {
+    fn from(shard: &ShardDoc) -> Self {
+        let stats = shard.stats();
+        Self {
+            search: stats.search_total() as f64,
+            size_in_bytes: stats.size_in_bytes() as f64,
+            write_load: stats.write_load(),
+        }
+    }
+}
+
+impl ShardLoad {
+    /// The recent load of a shard from its rates. A shard without any yet, such as one of a new
+    /// index, falls back to its lifetime write load, which is an average in the same unit, and
+    /// no search, since a lifetime count is not comparable with a rate.
+    pub fn recent(shard: &ShardDoc) -> Self {
+        let size_in_bytes = shard.stats().size_in_bytes() as f64;
+        match shard.rates() {
+            Some(rates) => Self {
+                search: rates.query_per_sec + rates.fetch_per_sec,
+                size_in_bytes,
+                write_load: rates.write_load,
+            },
+            None => Self {
+                search: 0.0,
+                size_in_bytes,
+                write_load: shard.stats().write_load(),
+            },
+        }
+    }
+}
+
+/// A balancing strategy decides how much each resource contributes to a shard's weight
+pub trait Strategy {
+    fn weigh(&self, share: &ShardLoad) -> f64;
+}
+
+/// Spread indexing work first, using disk size as a tie-breaker
+pub struct Ingest;
+
+impl Strategy for Ingest {
+    fn weigh(&self, share: &ShardLoad) -> f64 {
+        0.7 * share.write_load + 0.2 * share.size_in_bytes + 0.1 * share.search
+    }
+}
+
+/// Spread query and fetch traffic first, using disk size as a tie-breaker
+pub struct Search;
+
+impl Strategy for Search {
+    fn weigh(&self, share: &ShardLoad) -> f64 {
+        0.7 * share.search + 0.2 * share.size_in_bytes + 0.1 * share.write_load
+    }
+}
+
+/// Spread bytes on disk only
+pub struct Disk;
+
+impl Strategy for Disk {
+    fn weigh(&self, share: &ShardLoad) -> f64 {
+        share.size_in_bytes
+    }
+}
+
+/// Weigh indexing, search and disk equally
+pub struct Mixed;
+
+impl Strategy for Mixed {
+    fn weigh(&self, share: &ShardLoad) -> f64 {
+        (share.write_load + share.size_in_bytes + share.search) / 3.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum StrategyType {
+    Ingest,
+    Search,
+    Disk,
+    Mixed,
+}
+
+impl StrategyType {
+    pub fn strategy(&self) -> Box<dyn Strategy> {
+        match self {
+            Self::Ingest => Box::new(Ingest),
+            Self::Search => Box::new(Search),
+            Self::Disk => Box::new(Disk),
+            Self::Mixed => Box::new(Mixed),
+        }
+    }
+}
+
+impl std::fmt::Display for StrategyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ingest => write!(f, "ingest"),
+            Self::Search => write!(f, "search"),
+            Self::Disk => write!(f, "disk"),
+            Self::Mixed => write!(f, "mixed"),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::data::{IndexSettings, Node, ShardDoc};
+
+pub const TIERS: [&str; 5] = [
+    "data_content",
+    "data_hot",
+    "data_warm",
+    "data_cold",
+    "data_frozen",
+];
+
+/// The nodes of a tier with their capacities, and the shards balanced across them
+pub struct Tier {
+    /// Node names with their capacity relative to the tier, which averages 1
+    pub nodes: Vec<(String, f64)>,
+    /// Positions of the tier's shards in the evaluated shard list
+    pub shards: Vec<usize>,
+}
+
+/// The nodes that can hold shards of a tier, sorted by name for a stable plan
+pub fn tier_nodes<'a>(nodes: &'a [Node], tier: &str) -> Vec<&'a Node> {
+    let mut tier_nodes: Vec<&Node> = nodes.iter().filter(|node| node.in_tier(tier)).collect();
+    tier_nodes.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+    tier_nodes
+}
+
+/// The tier a shard should be balanced within, see [`index_tier`]
+pub fn shard_tier(shard: &ShardDoc, nodes: &[Node]) -> Option<&'static str> {
+    index_tier(shard.index()?, nodes)
+}
+
+/// The first preferred tier of an index that has any nodes. Without a preference, data stream
+/// backing indices default to `data_hot` and all other indices to `data_content`, matching
+/// Elasticsearch.
+pub fn index_tier(index: &IndexSettings, nodes: &[Node]) -> Option<&'static str> {
+    let preference = match index.tier_preference() {
+        preference if !preference.is_empty() => preference,
+        _ => match index.data_stream.is_some() {
+            true => vec![String::from("data_hot")],
+            false => vec![String::from("data_content")],
+        },
+    };
+    preference.iter().find_map(|preferred| {
+        TIERS
+            .into_iter()
+            .find(|tier| tier == preferred)
+            .filter(|tier| nodes.iter().any(|node| node.in_tier(tier)))
+    })
+}
@@ -1,18 +1,22 @@
+mod archive;
 mod directory;
 mod elasticsearch;
+mod replay;
 
-use crate::client::{Auth, AuthType, Host};
-use crate::config;
-use crate::data::ElasticsearchApi;
+use crate::client::AuthType;
+use crate::data::{
+    ClusterSettings, DataStreams, ElasticsearchApi, IndicesSettings, IndicesStats, Nodes,
+    NodesStats,
+};
 use color_eyre::eyre::{eyre, Result};
 use directory::DirectoryReceiver;
-use elasticsearch::ElasticsearchReceiver;
+pub use elasticsearch::{check_response, ElasticsearchReceiver};
+use replay::ReplayReceiver;
 use serde::de::DeserializeOwned;
 use std::path::Path;
-use url::Url;
 
 #[allow(dead_code)]
-trait Receive {
+pub trait Receive {
     async fn is_connected(&self) -> bool;
     async fn get<T>(&self) -> Result<T>
     where
@@ -22,44 +26,57 @@ trait Receive {
 pub enum Receiver {
     File(DirectoryReceiver),
     Elasticsearch(ElasticsearchReceiver),
+    Replay(ReplayReceiver),
 }
 
 impl Receiver {
     pub fn parse(input: &str, auth_type: &AuthType) -> Result<Self> {
         log::debug!("Parsing receiver: {}", input);
-        match Host::parse(input) {
-            Some(host) => {
-                let receiver = ElasticsearchReceiver::from_host(host)?;
-                return Ok(Self::Elasticsearch(receiver));
-            }
-            None => log::debug!("Input was not a known host"),
+        match ElasticsearchReceiver::parse(input, auth_type) {
+            Some(receiver) => return Ok(Self::Elasticsearch(receiver?)),
+            None => log::debug!("Input was not a valid URL"),
         }
-        // Attempt to parse the input as a URL
-        match Url::parse(input) {
-            Ok(url) => {
-                let auth = Auth::new(
-                    auth_type,
-                    config::ESHIPSTER_RC_USERNAME.clone(),
-                    config::ESHIPSTER_RC_PASSWORD.clone(),
-                    config::ESHIPSTER_RC_APIKEY.clone(),
-                );
-                let receiver = ElasticsearchReceiver::new(url, auth)?;
-                return Ok(Self::Elasticsearch(receiver));
-            }
-            Err(_) => log::debug!("Input was not a valid URL"),
-        };
 
         // Fallback to a file path
         let path = Path::new(&input);
-        match path.is_dir() {
-            true => {
+        match (path.is_dir(), path.is_file()) {
+            (true, _) => {
                 let file_receiver = DirectoryReceiver::new(path.to_path_buf())?;
-                return Ok(Self::File(file_receiver));
+                Ok(Self::File(file_receiver))
             }
-            false => Err(eyre!("Filesystem input must be a directory")),
+            (_, true) => {
+                let file_receiver = DirectoryReceiver::from_archive(path.to_path_buf())?;
+                Ok(Self::File(file_receiver))
+            }
+            _ => Err(eyre!("Filesystem input must be a directory or archive")),
         }
     }
 
+    /// Replay the snapshot subdirectories of `path` in capture order
+    pub fn replay(path: &str) -> Result<Self> {
+        Ok(Self::Replay(ReplayReceiver::new(
+            Path::new(path).to_path_buf(),
+        )?))
+    }
+
+    /// Move a replay to its next snapshot, returning false when there are none left. Other
+    /// receivers always read the current state, so never advance.
+    pub fn advance(&mut self) -> Result<bool> {
+        match self {
+            Receiver::Replay(replay_receiver) => replay_receiver.advance(),
+            _ => Ok(false),
+        }
+    }
+
+    /// Time the data was collected, which is now unless replaying a snapshot
+    pub fn timestamp(&self) -> i64 {
+        match self {
+            Receiver::Replay(replay_receiver) => replay_receiver.timestamp(),
+            _ => None,
+        }
+        .unwrap_or_else(|| chrono::Utc::now().timestamp_millis())
+    }
+
     pub async fn get<T>(&self) -> Result<T>
     where
         T: ElasticsearchApi + DeserializeOwned,
@@ -69,7 +86,38 @@ impl Receiver {
             Receiver::Elasticsearch(elasticsearch_receiver) => {
                 elasticsearch_receiver.get::<T>().await
             }
+            Receiver::Replay(replay_receiver) => replay_receiver.get::<T>().await,
+        }
+    }
+}
+
+impl Receiver {
+    /// Record the response of every API the planner reads into `dir`, so the cluster can be
+    /// evaluated offline later. Returns the number of files written.
+    pub async fn capture(&self, dir: &Path) -> Result<usize> {
+        let receiver = match self {
+            Receiver::Elasticsearch(elasticsearch_receiver) => elasticsearch_receiver,
+            _ => return Err(eyre!("Only an Elasticsearch host can be captured")),
+        };
+        let required = [
+            receiver.capture::<DataStreams>(dir).await?,
+            receiver.capture::<IndicesSettings>(dir).await?,
+            receiver.capture::<IndicesStats>(dir).await?,
+            receiver.capture::<Nodes>(dir).await?,
+        ];
+        // Evaluations go on without these, as they do against a live host
+        let optional = [
+            receiver.capture::<ClusterSettings>(dir).await,
+            receiver.capture::<NodesStats>(dir).await,
+        ];
+        let mut written = required.len();
+        for result in optional {
+            match result {
+                Ok(_) => written += 1,
+                Err(e) => log::warn!("Skipping an optional API: {e}"),
+            }
         }
+        Ok(written)
     }
 }
 
@@ -80,6 +128,7 @@ impl std::fmt::Display for Receiver {
             Receiver::Elasticsearch(elasticsearch_receiver) => {
                 write!(f, "elasticsearch {}", elasticsearch_receiver)
             }
+            Receiver::Replay(replay_receiver) => write!(f, "replay {}", replay_receiver),
         }
     }
 }
@@ -0,0 +1,36 @@
+use color_eyre::eyre::Result;
+use flate2::read::GzDecoder;
+use std::{fs::File, path::Path};
+use tempfile::TempDir;
+
+/// Archive formats produced by the Elastic support diagnostic
+pub enum Archive {
+    TarGz,
+    Zip,
+}
+
+impl Archive {
+    /// Detect the archive format from the file extension
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+
+    /// Extract the archive into a temporary directory, which is removed when dropped
+    pub fn extract(&self, path: &Path) -> Result<TempDir> {
+        let dir = tempfile::Builder::new().prefix("eshipster-").tempdir()?;
+        log::debug!("Extracting {} to {}", path.display(), dir.path().display());
+        let file = File::open(path)?;
+        match self {
+            Self::TarGz => tar::Archive::new(GzDecoder::new(file)).unpack(dir.path())?,
+            Self::Zip => zip::ZipArchive::new(file)?.extract(dir.path())?,
+        }
+        Ok(dir)
+    }
+}
@@ -1,11 +1,22 @@
+use super::archive::Archive;
 use super::Receive;
-use crate::data::ElasticsearchApi;
+use crate::data::{ElasticsearchApi, Nodes};
 use color_eyre::eyre::{eyre, Result};
 use serde::de::DeserializeOwned;
-use std::{fs::File, io::BufReader, path::PathBuf};
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+use tempfile::TempDir;
 
 pub struct DirectoryReceiver {
+    /// The directory holding the diagnostic files
     path: PathBuf,
+    /// The directory or archive given as input
+    source: PathBuf,
+    /// Keeps an extracted archive on disk until the receiver is dropped
+    _extracted: Option<TempDir>,
 }
 
 impl DirectoryReceiver {
@@ -13,7 +24,11 @@ impl DirectoryReceiver {
         match path.is_dir() {
             true => {
                 log::debug!("Directory is valid: {}", path.display());
-                Ok(Self { path })
+                Ok(Self {
+                    path: find_root(&path),
+                    source: path,
+                    _extracted: None,
+                })
             }
             false => {
                 log::debug!("Directory is invalid: {}", path.display());
@@ -24,6 +39,48 @@ impl DirectoryReceiver {
             }
         }
     }
+
+    /// Read a support diagnostic `.zip` or `.tar.gz` archive by extracting it to a temporary
+    /// directory
+    pub fn from_archive(path: PathBuf) -> Result<Self> {
+        let archive = Archive::detect(&path).ok_or(eyre!(
+            "Filesystem input must be a directory, .zip or .tar.gz: {}",
+            path.display()
+        ))?;
+        let extracted = archive.extract(&path)?;
+        Ok(Self {
+            path: find_root(extracted.path()),
+            source: path,
+            _extracted: Some(extracted),
+        })
+    }
+}
+
+/// Diagnostic bundles usually nest their files in a single top-level directory named after the
+/// diagnostic run, so descend into the first subdirectory that holds the node info
+fn find_root(path: &Path) -> PathBuf {
+    let marker = Nodes::file_name();
+    if path.join(&marker).is_file() {
+        return path.to_path_buf();
+    }
+    let mut subdirectories: Vec<PathBuf> = match path.read_dir() {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|entry| entry.is_dir())
+            .collect(),
+        Err(_) => return path.to_path_buf(),
+    };
+    subdirectories.sort();
+    match subdirectories
+        .into_iter()
+        .find(|subdirectory| subdirectory.join(&marker).is_file())
+    {
+        Some(root) => {
+            log::info!("Reading diagnostic files from {}", root.display());
+            root
+        }
+        None => path.to_path_buf(),
+    }
 }
 
 impl Receive for DirectoryReceiver {
@@ -38,9 +95,20 @@ impl Receive for DirectoryReceiver {
     where
         T: DeserializeOwned + ElasticsearchApi,
     {
-        let filename = &self.path.join(T::file_name());
+        let file_names: Vec<String> = std::iter::once(T::file_name())
+            .chain(T::alternative_file_names())
+            .collect();
+        let filename = file_names
+            .iter()
+            .map(|file_name| self.path.join(file_name))
+            .find(|filename| filename.is_file())
+            .ok_or(eyre!(
+                "None of {} found in {}",
+                file_names.join(", "),
+                self.path.display()
+            ))?;
         log::debug!("Reading file: {}", &filename.display());
-        let file = File::open(&filename)?;
+        let file = File::open(filename)?;
         let reader = BufReader::new(file);
         let data: T = serde_json::from_reader(reader)?;
         Ok(data)
@@ -49,6 +117,6 @@ impl Receive for DirectoryReceiver {
 
 impl std::fmt::Display for DirectoryReceiver {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.path.display())
+        write!(f, "{}", self.source.display())
     }
 }
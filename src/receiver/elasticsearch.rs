@@ -1,9 +1,13 @@
 use super::Receive;
-use crate::client::{Auth, ElasticsearchBuilder, Host};
+use crate::client::{Auth, AuthType, ElasticsearchBuilder, Host};
+use crate::config;
 use crate::data::ElasticsearchApi;
-use color_eyre::eyre::Result;
-use elasticsearch::{http, Elasticsearch};
+use color_eyre::eyre::{eyre, Result};
+use elasticsearch::{http, http::response::Response, Elasticsearch};
 use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
 use url::Url;
 
 pub struct ElasticsearchReceiver {
@@ -26,6 +30,31 @@ impl ElasticsearchReceiver {
         let client = ElasticsearchBuilder::from_host(host)?;
         Ok(Self { client, url })
     }
+
+    /// Connect to a known host, or to a URL with the `ESHIPSTER_RC_*` credentials. Returns None
+    /// when the input is neither.
+    pub fn parse(input: &str, auth_type: &AuthType) -> Option<Result<Self>> {
+        if let Some(host) = Host::parse(input) {
+            return Some(Self::from_host(host));
+        }
+        log::debug!("Input was not a known host");
+        let url = Url::parse(input).ok()?;
+        let auth = Auth::new(
+            auth_type,
+            config::ESHIPSTER_RC_USERNAME.clone(),
+            config::ESHIPSTER_RC_PASSWORD.clone(),
+            config::ESHIPSTER_RC_APIKEY.clone(),
+        );
+        Some(Self::new(url, auth))
+    }
+
+    pub fn client(&self) -> &Elasticsearch {
+        &self.client
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
 }
 
 impl Receive for ElasticsearchReceiver {
@@ -63,6 +92,16 @@ impl Receive for ElasticsearchReceiver {
     where
         T: ElasticsearchApi + DeserializeOwned,
     {
+        let response = check_response(self.send::<T>().await?).await?;
+
+        // turbo-fish serde deserialization of the JSON response
+        response.json::<T>().await.map_err(Into::into)
+    }
+}
+
+impl ElasticsearchReceiver {
+    /// Send a GET request to the API path of the provided type
+    async fn send<T: ElasticsearchApi>(&self) -> Result<Response> {
         // Get the API URL path for the provided type
         let path = T::url_path();
         log::debug!("Getting API: {}", &path);
@@ -79,9 +118,40 @@ impl Receive for ElasticsearchReceiver {
                 None,
             )
             .await?;
+        Ok(response)
+    }
 
-        // turbo-fish serde deserialization of the JSON response
-        response.json::<T>().await.map_err(Into::into)
+    /// Write the unparsed response of an API to its file name under `dir`, the layout read by
+    /// the directory receiver. Returns the path written.
+    pub async fn capture<T: ElasticsearchApi>(&self, dir: &Path) -> Result<PathBuf> {
+        let response = self.send::<T>().await?;
+        let status = response.status_code();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(eyre!("{} failed with {status}: {body}", T::url_path()));
+        }
+        let path = dir.join(T::file_name());
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, body)?;
+        log::debug!("Captured {} to {}", T::url_path(), path.display());
+        Ok(path)
+    }
+}
+
+/// Turn an unsuccessful response into an error carrying Elasticsearch's reason
+pub async fn check_response(response: Response) -> Result<Response> {
+    match response.status_code().is_success() {
+        true => Ok(response),
+        false => {
+            let body = response.json::<Value>().await?;
+            let reason = body["error"]["reason"]
+                .as_str()
+                .map(String::from)
+                .unwrap_or(body.to_string());
+            Err(eyre!("{reason}"))
+        }
     }
 }
 
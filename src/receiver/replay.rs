@@ -0,0 +1,120 @@
+use super::directory::DirectoryReceiver;
+use super::Receive;
+use crate::data::ElasticsearchApi;
+use chrono::{DateTime, NaiveDateTime};
+use color_eyre::eyre::{eyre, Result};
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Replays a directory of captured snapshots in time order, one snapshot per evaluation
+pub struct ReplayReceiver {
+    path: PathBuf,
+    /// Capture time in epoch milliseconds and directory of each snapshot, oldest first
+    snapshots: Vec<(i64, PathBuf)>,
+    /// Position in `snapshots` and receiver of the snapshot being replayed
+    current: Option<(usize, DirectoryReceiver)>,
+}
+
+impl ReplayReceiver {
+    pub fn new(path: PathBuf) -> Result<Self> {
+        let mut snapshots: Vec<(i64, PathBuf)> = path
+            .read_dir()?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|entry| entry.is_dir())
+            .filter_map(|snapshot| match snapshot_time(&snapshot) {
+                Some(timestamp) => Some((timestamp, snapshot)),
+                None => {
+                    log::warn!(
+                        "Skipping {}, its capture time is unknown",
+                        snapshot.display()
+                    );
+                    None
+                }
+            })
+            .collect();
+        if snapshots.is_empty() {
+            return Err(eyre!("No snapshots to replay in {}", path.display()));
+        }
+        snapshots.sort();
+        log::info!(
+            "Replaying {} snapshots from {}",
+            snapshots.len(),
+            path.display()
+        );
+        Ok(Self {
+            path,
+            snapshots,
+            current: None,
+        })
+    }
+
+    /// Move to the next snapshot, returning false once every snapshot has been replayed
+    pub fn advance(&mut self) -> Result<bool> {
+        let next = self.current.as_ref().map_or(0, |(i, _)| i + 1);
+        let Some((_, snapshot)) = self.snapshots.get(next) else {
+            self.current = None;
+            return Ok(false);
+        };
+        log::info!(
+            "Replaying snapshot {} of {}: {}",
+            next + 1,
+            self.snapshots.len(),
+            snapshot.display()
+        );
+        self.current = Some((next, DirectoryReceiver::new(snapshot.clone())?));
+        Ok(true)
+    }
+
+    /// Capture time of the snapshot being replayed
+    pub fn timestamp(&self) -> Option<i64> {
+        self.current.as_ref().map(|(i, _)| self.snapshots[*i].0)
+    }
+}
+
+/// Snapshots are named after their capture time, as RFC 3339, `%Y%m%dT%H%M%SZ` or epoch
+/// milliseconds. Otherwise the modification time of the indices stats file is used.
+fn snapshot_time(snapshot: &Path) -> Option<i64> {
+    let name = snapshot.file_name()?.to_str()?;
+    if let Ok(time) = DateTime::parse_from_rfc3339(name) {
+        return Some(time.timestamp_millis());
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(name, "%Y%m%dT%H%M%SZ") {
+        return Some(time.and_utc().timestamp_millis());
+    }
+    if let Ok(millis) = name.parse::<i64>() {
+        return Some(millis);
+    }
+    let modified = snapshot
+        .join(crate::data::IndicesStats::file_name())
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    let millis = modified.duration_since(UNIX_EPOCH).ok()?.as_millis();
+    i64::try_from(millis).ok()
+}
+
+impl Receive for ReplayReceiver {
+    async fn is_connected(&self) -> bool {
+        self.current.is_some()
+    }
+
+    async fn get<T>(&self) -> Result<T>
+    where
+        T: DeserializeOwned + ElasticsearchApi,
+    {
+        match &self.current {
+            Some((_, snapshot)) => snapshot.get::<T>().await,
+            None => Err(eyre!("No snapshot is being replayed")),
+        }
+    }
+}
+
+impl std::fmt::Display for ReplayReceiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.current {
+            Some((_, snapshot)) => write!(f, "{}", snapshot),
+            None => write!(f, "{}", self.path.display()),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use crate::enforcer::Enforcer;
+use crate::exporter::Exporter;
+use crate::processor::{self, BalanceOptions, Plan, RateTracker};
+use crate::receiver::Receiver;
+use color_eyre::eyre::Result;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Re-evaluates shard balance on an interval until a shutdown signal is received
+pub struct Watcher {
+    enforcer: Option<Enforcer>,
+    exporter: Exporter,
+    interval: Duration,
+    options: BalanceOptions,
+    rates: RateTracker,
+    receiver: Receiver,
+}
+
+impl Watcher {
+    pub fn new(
+        receiver: Receiver,
+        exporter: Exporter,
+        interval: Duration,
+        options: BalanceOptions,
+    ) -> Self {
+        Self {
+            enforcer: None,
+            exporter,
+            interval,
+            options,
+            rates: RateTracker::default(),
+            receiver,
+        }
+    }
+
+    /// Enforce the planned moves on each cycle instead of only reporting them
+    pub fn enforcer(self, enforcer: Option<Enforcer>) -> Self {
+        Self { enforcer, ..self }
+    }
+
+    pub async fn run(mut self) -> Result<()> {
+        let mut shutdown = shutdown_signal();
+        self.enforcer = self
+            .enforcer
+            .take()
+            .map(|enforcer| enforcer.shutdown(shutdown.clone()));
+        let mut cycle: u64 = 0;
+        log::info!("Watching {} every {:?}", self.receiver, self.interval);
+
+        while !*shutdown.borrow() {
+            cycle += 1;
+            log::info!("Starting watch cycle {cycle}");
+            // A cycle always runs to completion so bulk batches are never cut off mid-request
+            if let Err(e) = self.cycle().await {
+                log::error!("Watch cycle {cycle} failed: {e}");
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(self.interval) => {}
+                _ = shutdown.changed() => {}
+            }
+        }
+
+        log::info!("Watch stopped after {cycle} cycles");
+        Ok(())
+    }
+
+    /// Evaluate every snapshot of a replay receiver in turn, with rates between consecutive
+    /// snapshots as if they had been watched live. Returns the number of snapshots evaluated.
+    pub async fn replay(mut self) -> Result<usize> {
+        let mut cycles = 0;
+        while self.receiver.advance()? {
+            cycles += 1;
+            if let Err(e) = self.cycle().await {
+                log::error!("Replay of {} failed: {e}", self.receiver);
+            }
+        }
+        log::info!("Replayed {cycles} snapshots");
+        Ok(cycles)
+    }
+
+    async fn cycle(&mut self) -> Result<()> {
+        let evaluation =
+            processor::evaluate_shard_balance(&self.receiver, &self.options, Some(&mut self.rates))
+                .await?;
+        let plan = Plan::from(&evaluation);
+        let docs = evaluation.shards;
+        log::debug!("Shard balance plan:\n{plan}");
+        let summary = plan.summary(self.options.strategy);
+        if let Some(enforcer) = &self.enforcer {
+            let moved = enforcer.enforce(&docs, &plan.moves).await?;
+            log::info!("Moved {moved} of {} shards on {enforcer}", plan.moves.len());
+        }
+
+        let doc_count = self.exporter.write(docs).await?;
+        log::info!("Wrote {doc_count} docs to {}", self.exporter);
+        self.exporter.write(evaluation.nodes).await?;
+        self.exporter.write(vec![summary]).await?;
+        Ok(())
+    }
+}
+
+/// Spawn a listener that flips the returned channel on SIGINT or SIGTERM. A second SIGINT exits
+/// right away instead of waiting for the cycle to finish.
+fn shutdown_signal() -> watch::Receiver<bool> {
+    let (sender, receiver) = watch::channel(false);
+    tokio::spawn(async move {
+        let ctrl_c = tokio::signal::ctrl_c();
+        #[cfg(unix)]
+        let terminate = async {
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                Ok(mut signal) => {
+                    signal.recv().await;
+                }
+                Err(e) => {
+                    log::error!("Failed to listen for SIGTERM: {e}");
+                    std::future::pending::<()>().await;
+                }
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = ctrl_c => log::info!(
+                "Received SIGINT, shutting down after the current cycle, press Ctrl-C again to exit now"
+            ),
+            _ = terminate => log::info!("Received SIGTERM, shutting down after the current cycle"),
+        }
+        let _ = sender.send(true);
+
+        if tokio::signal::ctrl_c().await.is_ok() {
+            log::warn!("Received another SIGINT, exiting without finishing the current cycle");
+            std::process::exit(130);
+        }
+    });
+    receiver
+}